@@ -1,12 +1,40 @@
 use syn::{
+    bracketed,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
-    Ident, Result, Token, Type, Visibility,
+    token, Block, Error, FieldsNamed, Ident, LitInt, LitStr, Path, Result, Token, Type, Visibility,
 };
 
+use crate::types::{Annotations, Naming, Options};
+
 pub enum VariantArg {
-    Variant { typ: Type, name: Option<Ident> },
-    Composed { typ: Type },
+    Variant {
+        typ: Type,
+        context: Option<Box<Type>>,
+        name: Option<Ident>,
+        annotations: Option<Annotations>,
+    },
+    Composed {
+        typ: Type,
+    },
+    Inline {
+        name: Ident,
+        fields: Option<FieldsNamed>,
+        message: LitStr,
+        annotations: Option<Annotations>,
+    },
+}
+
+fn peek_with(input: ParseStream) -> bool {
+    input
+        .fork()
+        .parse::<Ident>()
+        .is_ok_and(|keyword| keyword == "with")
+}
+
+fn peek_inline(input: ParseStream) -> bool {
+    input.peek(Ident)
+        && (input.peek2(token::Brace) || (input.peek2(Token!(=)) && !input.peek2(Token!(=>))))
 }
 
 impl Parse for VariantArg {
@@ -16,8 +44,40 @@ impl Parse for VariantArg {
         if break_tok.is_some() {
             let typ: Type = input.parse()?;
             Ok(VariantArg::Composed { typ })
+        } else if peek_inline(input) {
+            let name = input.parse()?;
+
+            let fields = if input.peek(token::Brace) {
+                Some(input.parse()?)
+            } else {
+                None
+            };
+
+            input.parse::<Token!(=)>()?;
+            let message = input.parse()?;
+
+            let annotations = if input.peek(token::Bracket) {
+                Some(input.parse()?)
+            } else {
+                None
+            };
+
+            Ok(VariantArg::Inline {
+                name,
+                fields,
+                message,
+                annotations,
+            })
         } else {
             let typ: Type = input.parse()?;
+
+            let context: Option<Box<Type>> = if peek_with(input) {
+                input.parse::<Ident>()?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
+
             let as_tok: Option<Token!(as)> = input.parse()?;
 
             let name: Option<Ident> = if as_tok.is_some() {
@@ -26,11 +86,229 @@ impl Parse for VariantArg {
                 None
             };
 
-            Ok(VariantArg::Variant { typ, name })
+            let annotations = if input.peek(token::Bracket) {
+                Some(input.parse()?)
+            } else {
+                None
+            };
+
+            Ok(VariantArg::Variant {
+                typ,
+                context,
+                name,
+                annotations,
+            })
         }
     }
 }
 
+impl Parse for Annotations {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let bracket = bracketed!(content in input);
+
+        let mut annotations = Annotations::default();
+
+        while !content.is_empty() {
+            let key: Ident = content.parse()?;
+
+            match key.to_string().as_str() {
+                "code" => {
+                    content.parse::<Token!(=)>()?;
+                    set_once(&mut annotations.code, content.parse()?, &key)?;
+                }
+                "grpc" => {
+                    content.parse::<Token!(=)>()?;
+                    set_once(&mut annotations.grpc, content.parse()?, &key)?;
+                }
+                "serde" => set_flag(&mut annotations.serde, &key)?,
+                "status" => {
+                    content.parse::<Token!(=)>()?;
+                    let status: LitInt = content.parse()?;
+                    let value: u16 = status.base10_parse()?;
+
+                    if !(100..=999).contains(&value) {
+                        return Err(Error::new_spanned(
+                            status,
+                            "status code must be between 100 and 999",
+                        ));
+                    }
+
+                    set_once(&mut annotations.status, value, &key)?;
+                }
+                "response" => set_flag(&mut annotations.response, &key)?,
+                "shared" => set_flag(&mut annotations.shared, &key)?,
+                "exit" => {
+                    content.parse::<Token!(=)>()?;
+                    let exit: LitInt = content.parse()?;
+                    let value: u8 = exit.base10_parse()?;
+
+                    if value == 0 {
+                        return Err(Error::new_spanned(
+                            exit,
+                            "exit code 0 indicates success and cannot be used for errors",
+                        ));
+                    }
+
+                    set_once(&mut annotations.exit, value, &key)?;
+                }
+                "io_kind" => {
+                    content.parse::<Token!(=)>()?;
+                    set_once(&mut annotations.io_kind, content.parse()?, &key)?;
+                }
+                _ => return Err(Error::new_spanned(&key, "unknown variant annotation")),
+            }
+
+            if content.is_empty() {
+                break;
+            }
+
+            content.parse::<Token!(,)>()?;
+        }
+
+        if annotations.response && annotations.status.is_some() {
+            return Err(Error::new(
+                bracket.span.join(),
+                "`status` cannot be combined with `response`",
+            ));
+        }
+
+        Ok(annotations)
+    }
+}
+
+const OPTIONS: &[&str] = &[
+    "metadata",
+    "report",
+    "io_error",
+    "naming",
+    "name",
+    "module",
+    "infer",
+    "inferable",
+    "result",
+    "nested",
+    "auto_ok",
+    "shared",
+    "partial_eq",
+];
+
+fn peek_option(input: ParseStream) -> bool {
+    let fork = input.fork();
+
+    let Ok(key) = fork.parse::<Ident>() else {
+        return false;
+    };
+
+    OPTIONS.contains(&key.to_string().as_str())
+        && (fork.is_empty() || fork.peek(Token!(,)) || fork.peek(Token!(=)))
+}
+
+impl Parse for Options {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut options = Options::default();
+
+        while peek_option(input) {
+            let key: Ident = input.parse()?;
+
+            match key.to_string().as_str() {
+                "metadata" => {
+                    input.parse::<Token!(=)>()?;
+                    let metadata: LitStr = input.parse()?;
+                    let value = metadata.value();
+
+                    let valid = !value.is_empty()
+                        && !value.ends_with("-bin")
+                        && value
+                            .bytes()
+                            .all(|c| matches!(c, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.'));
+
+                    if !valid {
+                        return Err(Error::new_spanned(
+                            metadata,
+                            "metadata key must be a lowercase ASCII string not ending with `-bin`",
+                        ));
+                    }
+
+                    set_once(&mut options.metadata, metadata, &key)?;
+                }
+                "report" => set_once(&mut options.report, key.clone(), &key)?,
+                "io_error" => set_once(&mut options.io_error, key.clone(), &key)?,
+                "naming" => {
+                    input.parse::<Token!(=)>()?;
+                    let value: Ident = input.parse()?;
+
+                    let naming = match value.to_string().as_str() {
+                        "full" => Naming::Full,
+                        "last_two" => Naming::LastTwo,
+                        "last" => Naming::Last,
+                        _ => {
+                            return Err(Error::new_spanned(
+                                value,
+                                "naming must be one of `full`, `last_two` or `last`",
+                            ))
+                        }
+                    };
+
+                    set_once(&mut options.naming, (key.clone(), naming), &key)?;
+                }
+                "name" => {
+                    input.parse::<Token!(=)>()?;
+                    set_once(&mut options.name, input.parse()?, &key)?;
+                }
+                "module" => set_once(&mut options.module, key.clone(), &key)?,
+                "infer" => set_once(&mut options.infer, key.clone(), &key)?,
+                "inferable" => set_once(&mut options.inferable, key.clone(), &key)?,
+                "result" => {
+                    input.parse::<Token!(=)>()?;
+                    set_once(
+                        &mut options.result,
+                        input.call(Path::parse_mod_style)?,
+                        &key,
+                    )?;
+                }
+                "nested" => set_once(&mut options.nested, key.clone(), &key)?,
+                "auto_ok" => set_once(&mut options.auto_ok, key.clone(), &key)?,
+                "shared" => set_once(&mut options.shared, key.clone(), &key)?,
+                "partial_eq" => set_once(&mut options.partial_eq, key.clone(), &key)?,
+                _ => unreachable!("all options should be handled"),
+            }
+
+            if input.is_empty() {
+                break;
+            }
+
+            input.parse::<Token!(,)>()?;
+        }
+
+        Ok(options)
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, key: &Ident) -> Result<()> {
+    if slot.is_some() {
+        return Err(Error::new_spanned(
+            key,
+            format!("`{key}` is specified twice"),
+        ));
+    }
+
+    *slot = Some(value);
+    Ok(())
+}
+
+fn set_flag(flag: &mut bool, key: &Ident) -> Result<()> {
+    if *flag {
+        return Err(Error::new_spanned(
+            key,
+            format!("`{key}` is specified twice"),
+        ));
+    }
+
+    *flag = true;
+    Ok(())
+}
+
 pub struct TypeDef {
     pub visibility: Visibility,
     pub name: Ident,
@@ -49,12 +327,14 @@ impl Parse for TypeDef {
 pub type VariantArgs = Punctuated<VariantArg, Token!(|)>;
 
 pub struct DefineErrorArgs {
+    pub options: Options,
     pub type_def: TypeDef,
     pub variants: VariantArgs,
 }
 
 impl Parse for DefineErrorArgs {
     fn parse(input: ParseStream) -> Result<Self> {
+        let options = input.parse()?;
         let type_def = input.parse()?;
         let equal: Option<Token!(=)> = input.parse()?;
 
@@ -64,17 +344,50 @@ impl Parse for DefineErrorArgs {
             VariantArgs::default()
         };
 
-        Ok(DefineErrorArgs { type_def, variants })
+        Ok(DefineErrorArgs {
+            options,
+            type_def,
+            variants,
+        })
     }
 }
 
 pub struct ThrowsArgs {
+    pub options: Options,
+    pub anon: Option<Ident>,
     pub name: Option<Ident>,
     pub variants: VariantArgs,
 }
 
+fn peek_anon(input: ParseStream) -> bool {
+    let fork = input.fork();
+
+    let Ok(keyword) = fork.parse::<Ident>() else {
+        return false;
+    };
+
+    keyword == "anon"
+        && !fork.is_empty()
+        && !fork.peek(Token!(|))
+        && !fork.peek(Token!(::))
+        && !fork.peek(Token!(<))
+        && !fork.peek(Token!(as))
+        && !fork.peek(token::Bracket)
+}
+
 impl Parse for ThrowsArgs {
     fn parse(input: ParseStream) -> Result<Self> {
+        let options = input.parse()?;
+
+        if peek_anon(input) {
+            return Ok(ThrowsArgs {
+                options,
+                anon: Some(input.parse()?),
+                name: None,
+                variants: Punctuated::parse_terminated(input)?,
+            });
+        }
+
         let type_tok: Option<Token!(type)> = input.parse()?;
 
         if type_tok.is_some() {
@@ -89,14 +402,100 @@ impl Parse for ThrowsArgs {
             };
 
             Ok(ThrowsArgs {
+                options,
+                anon: None,
                 name: Some(name),
                 variants,
             })
         } else {
             Ok(ThrowsArgs {
+                options,
+                anon: None,
                 name: None,
                 variants: Punctuated::parse_terminated(input)?,
             })
         }
     }
 }
+
+pub struct PaletteEntry {
+    pub alias: Ident,
+    pub typ: Type,
+    pub annotations: Option<Annotations>,
+}
+
+impl Parse for PaletteEntry {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let alias = input.parse()?;
+        input.parse::<Token!(=)>()?;
+        let typ = input.parse()?;
+
+        let annotations = if input.peek(token::Bracket) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(PaletteEntry {
+            alias,
+            typ,
+            annotations,
+        })
+    }
+}
+
+pub struct ThrowsModuleArgs {
+    pub palette: Punctuated<PaletteEntry, Token!(,)>,
+}
+
+impl Parse for ThrowsModuleArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(ThrowsModuleArgs {
+            palette: Punctuated::parse_terminated(input)?,
+        })
+    }
+}
+
+pub struct TryBlockArgs {
+    pub options: Options,
+    pub name: Option<Ident>,
+    pub variants: VariantArgs,
+    pub block: Block,
+}
+
+impl Parse for TryBlockArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let options = input.parse()?;
+        let type_tok: Option<Token!(type)> = input.parse()?;
+
+        let name = if type_tok.is_some() {
+            let name = input.parse()?;
+            input.parse::<Token!(=)>()?;
+            Some(name)
+        } else {
+            None
+        };
+
+        let mut variants = VariantArgs::new();
+
+        while !input.peek(Token!(=>)) {
+            variants.push_value(input.parse()?);
+
+            if input.peek(Token!(=>)) {
+                break;
+            }
+
+            variants.push_punct(input.parse()?);
+        }
+
+        input.parse::<Token!(=>)>()?;
+        let block = input.parse()?;
+
+        Ok(TryBlockArgs {
+            options,
+            name,
+            variants,
+            block,
+        })
+    }
+}
@@ -1,58 +1,434 @@
-use crate::types::{CompositeError, Variant};
-use proc_macro2::{Ident, TokenStream};
-use quote::quote;
-use syn::{parse_quote, ItemFn, ReturnType, Type};
+use crate::{
+    interop::interop_impls,
+    names::{type_to_string, variant_to_method},
+    rewrite::{
+        convert_throw, convert_try_in_option, convert_try_to_union, nest_super_paths,
+        qualify_error_name, wrap_in_ok,
+    },
+    types::{CompositeError, InlineVariant, Options, Variant},
+};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{
+    parse_quote, spanned::Spanned, Block, Error, Field, GenericArgument, ItemFn, Path,
+    PathArguments, ReturnType, Type, TypePath,
+};
 
 fn error_enum(error: &CompositeError) -> TokenStream {
     let CompositeError {
         visibility,
         name,
         variants,
+        inline,
         ..
     } = error;
 
+    // A recursive variant is named after the error itself
+    let allow = variants
+        .iter()
+        .any(|v| v.name == *name)
+        .then(|| quote!(#[allow(clippy::enum_variant_names)]));
+
     let variants = variants
         .iter()
-        .map(|Variant { name, typ }| quote!(#name(#typ)));
+        .map(|Variant { name, typ, .. }| quote!(#name(#typ)))
+        .chain(
+            inline
+                .iter()
+                .map(|InlineVariant { name, fields, .. }| quote!(#name #fields)),
+        );
 
     quote!(
         #[derive(::core::fmt::Debug)]
+        #allow
         #visibility enum #name {
             #(#variants),*
         }
     )
 }
 
+/// Implements `Clone` for errors with shared variants.
+///
+/// The variants are cloned through `clone_variant` instead of deriving `Clone`,
+/// so that a variant that can't be cloned is reported at its type.
+fn impl_clone(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let arms = variants
+        .iter()
+        .map(|Variant { name: variant, typ, .. }| {
+            quote_spanned!(typ.span()=>
+                #name::#variant(e) => #name::#variant(::throwing::__private::clone_variant::<#typ>(e))
+            )
+        })
+        .chain(inline.iter().map(|InlineVariant { name: variant, fields, .. }| {
+            let fields: Vec<_> = fields.iter().flat_map(|f| &f.named).collect();
+            let idents = fields.iter().map(|f| &f.ident);
+            let clones = fields.iter().map(|Field { ident, ty, .. }| {
+                let clone = quote_spanned!(ty.span()=> ::throwing::__private::clone_variant::<#ty>(#ident));
+                quote!(#ident: #clone)
+            });
+
+            quote!(#name::#variant { #(#idents),* } => #name::#variant { #(#clones),* })
+        }));
+
+    let body = if variants.is_empty() && inline.is_empty() {
+        quote!(match *self {})
+    } else {
+        quote!(
+            match self {
+                #(#arms),*
+            }
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::core::clone::Clone for #name {
+            fn clone(&self) -> Self {
+                #body
+            }
+        }
+    )
+}
+
 fn impl_from_variant(error: &CompositeError, variant: &Variant) -> TokenStream {
     let error_name = &error.name;
-    let Variant { typ, name } = variant;
+    let Variant {
+        typ,
+        name,
+        annotations,
+        ..
+    } = variant;
 
-    quote!(
+    let mut stream = quote!(
         #[automatically_derived]
         impl ::core::convert::From<#typ> for #error_name {
             fn from(value: #typ) -> #error_name {
                 #error_name::#name(value)
             }
         }
+    );
+
+    let inner = annotations.shared.then(|| shared_type(typ));
+
+    // A shared recursive variant would conflict with the blanket `From<T> for T`
+    if let Some(inner) = inner.filter(|inner| !is_error_name(inner, error_name)) {
+        stream.extend(quote!(
+            #[automatically_derived]
+            impl ::core::convert::From<#inner> for #error_name {
+                fn from(value: #inner) -> #error_name {
+                    #error_name::#name(::std::sync::Arc::new(value))
+                }
+            }
+        ));
+    }
+
+    stream
+}
+
+fn is_error_name(typ: &Type, name: &Ident) -> bool {
+    matches!(typ, Type::Path(path) if path.qself.is_none() && path.path.is_ident(name))
+}
+
+/// Returns the first generic argument of a type like `Box<T>`, if it's a type.
+fn type_argument(typ: &Type) -> Option<&Type> {
+    let Type::Path(path) = typ else {
+        return None;
+    };
+
+    let PathArguments::AngleBracketed(args) = &path.path.segments.last()?.arguments else {
+        return None;
+    };
+
+    match args.args.first() {
+        Some(GenericArgument::Type(typ)) => Some(typ),
+        _ => None,
+    }
+}
+
+/// Returns whether the type is the `Box` that a recursive variant is stored in.
+fn is_boxed_error(typ: &Type, name: &Ident) -> bool {
+    let Type::Path(path) = typ else {
+        return false;
+    };
+
+    path.path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "Box")
+        && type_argument(typ).is_some_and(|inner| is_error_name(inner, name))
+}
+
+/// Returns an expression for the error held by a variant whose value is bound to `e`,
+/// looking through its context and the `Arc` or `Box` it may be stored in.
+fn held_error(error_name: &Ident, variant: &Variant) -> TokenStream {
+    let Variant {
+        typ,
+        context,
+        annotations,
+        ..
+    } = variant;
+
+    match context {
+        // The error of a variant with context is the first argument of `WithContext`
+        Some(_) if type_argument(typ).is_some_and(|inner| is_boxed_error(inner, error_name)) => {
+            quote!(&*e.error)
+        }
+        Some(_) => quote!(&e.error),
+        None if annotations.shared || is_boxed_error(typ, error_name) => quote!(&**e),
+        None => quote!(e),
+    }
+}
+
+/// Returns the type contained in the `Arc` of a shared variant.
+fn shared_type(typ: &Type) -> &Type {
+    let Type::Path(path) = typ else {
+        unreachable!("shared variants should be wrapped in an `Arc`");
+    };
+
+    let PathArguments::AngleBracketed(args) = &path.path.segments.last().unwrap().arguments else {
+        unreachable!("shared variants should be wrapped in an `Arc`");
+    };
+
+    match args.args.first() {
+        Some(GenericArgument::Type(typ)) => typ,
+        _ => unreachable!("shared variants should be wrapped in an `Arc`"),
+    }
+}
+
+fn impl_constructors(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        visibility,
+        name: error_name,
+        inline,
+        ..
+    } = error;
+
+    let constructors = inline.iter().map(|InlineVariant { name, fields, .. }| {
+        let method = variant_to_method(name);
+        let doc = format!(" Creates a new [`{error_name}::{name}`] error.");
+
+        let (params, args) = match fields {
+            Some(fields) => {
+                let params = fields.named.iter().map(|field| {
+                    let ident = &field.ident;
+                    let typ = &field.ty;
+                    quote!(#ident: #typ)
+                });
+
+                let args = fields.named.iter().map(|field| &field.ident);
+
+                (quote!(#(#params),*), quote!({ #(#args),* }))
+            }
+            None => (quote!(), quote!()),
+        };
+
+        quote!(
+            #[doc = #doc]
+            #visibility fn #method(#params) -> #error_name {
+                #error_name::#name #args
+            }
+        )
+    });
+
+    quote!(
+        #[automatically_derived]
+        impl #error_name {
+            #(#constructors)*
+        }
+    )
+}
+
+fn impl_combinators(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        visibility,
+        name: error_name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    // Inline variants can't be converted into another error,
+    // so only the errors without them can be mapped
+    let maps = inline.is_empty().then(|| {
+        variants.iter().map(|variant| {
+            let Variant { name, typ, .. } = variant;
+            let method = format_ident!("map_{}", variant_to_method(name));
+            let doc = format!(
+                " Converts the error into another error, transforming the [`{error_name}::{name}`] variant using `f`."
+            );
+
+            let others: Vec<&Variant> = variants
+                .iter()
+                .filter(|other| other.name != *name)
+                .collect();
+
+            let bounds = others
+                .iter()
+                .map(|Variant { typ, .. }| quote!(::core::convert::From<#typ>));
+
+            let arms = others.iter().map(|Variant { name: other, .. }| {
+                quote!(#error_name::#other(e) => ::core::convert::From::from(e))
+            });
+
+            quote!(
+                #[doc = #doc]
+                #visibility fn #method<__E, __U, __F>(self, f: __F) -> __E
+                where
+                    __F: ::core::ops::FnOnce(#typ) -> __U,
+                    __E: ::core::convert::From<__U> #(+ #bounds)*,
+                {
+                    match self {
+                        #error_name::#name(e) => ::core::convert::From::from(f(e)),
+                        #(#arms),*
+                    }
+                }
+            )
+        })
+    });
+
+    let maps = maps.into_iter().flatten();
+
+    let params: Vec<Ident> = variants
+        .iter()
+        .map(|v| &v.name)
+        .chain(inline.iter().map(|v| &v.name))
+        .map(variant_to_method)
+        .collect();
+
+    let generics: Vec<Ident> = (0..params.len()).map(|i| format_ident!("__F{i}")).collect();
+
+    let bounds = variants
+        .iter()
+        .map(|Variant { typ, .. }| quote!(#typ))
+        .chain(inline.iter().map(|InlineVariant { fields, .. }| {
+            let types = fields.iter().flat_map(|f| &f.named).map(|f| &f.ty);
+            quote!(#(#types),*)
+        }));
+
+    let arms = variants
+        .iter()
+        .zip(&params)
+        .map(|(Variant { name, .. }, param)| quote!(#error_name::#name(e) => #param(e)))
+        .chain(inline.iter().zip(&params[variants.len()..]).map(
+            |(InlineVariant { name, fields, .. }, param)| {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .flat_map(|f| &f.named)
+                    .map(|f| &f.ident)
+                    .collect();
+                quote!(#error_name::#name { #(#fields),* } => #param(#(#fields),*))
+            },
+        ));
+
+    quote!(
+        #[automatically_derived]
+        impl #error_name {
+            #(#maps)*
+
+            /// Converts the error into a value by calling the function for its variant.
+            ///
+            /// The functions are passed in the order of the variants, with inline variants last.
+            /// Functions for inline variants receive their fields in the order they were declared.
+            #[allow(clippy::too_many_arguments)]
+            #visibility fn fold<__R, #(#generics),*>(self, #(#params: #generics),*) -> __R
+            where
+                #(#generics: ::core::ops::FnOnce(#bounds) -> __R,)*
+            {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
     )
 }
 
 fn impl_from_composed(error: &CompositeError, typ: &Type) -> TokenStream {
     let error_name = &error.name;
 
+    // The conversion only uses `SubError` if it's implemented, and otherwise falls back
+    // to a method that requires every variant to be present, so that a missing variant
+    // is reported by name instead of through the bounds of `SubError`.
+    // The method is located at the suberror, which is where the error is reported.
+    let break_into = Ident::new("break_into", typ.span());
+
     quote!(
         #[automatically_derived]
         impl ::core::convert::From<#typ> for #error_name {
             fn from(value: #typ) -> #error_name {
-                ::throwing::SubError::to_super_error(value)
+                #[allow(unused_imports)]
+                use ::throwing::__private::{BreakInto as _, CheckBreak as _};
+
+                ::throwing::__private::Break::<#typ, #error_name>(value, ::core::marker::PhantomData)
+                    .#break_into()
             }
         }
     )
 }
 
+fn impl_variants(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        composed,
+        ..
+    } = error;
+
+    // Inline variants can't be converted into other errors,
+    // so an error containing them can't be a suberror
+    let list = inline.is_empty().then(|| {
+        let list = variants.iter().rev().fold(
+            quote!(()),
+            |rest, Variant { typ, .. }| quote!((#typ, #rest)),
+        );
+
+        quote!(
+            #[automatically_derived]
+            impl ::throwing::__private::Variants for #name {
+                type List = #list;
+            }
+        )
+    });
+
+    let indices = 0..variants.len();
+    let variant_types = variants.iter().map(|Variant { typ, .. }| typ);
+
+    let types = variants
+        .iter()
+        .map(|Variant { typ, .. }| typ)
+        .chain(composed);
+
+    quote!(
+        #list
+
+        #(
+            #[automatically_derived]
+            impl<T> ::throwing::__private::HasVariant<#types, T> for #name {}
+        )*
+
+        #(
+            #[automatically_derived]
+            impl ::throwing::__private::VariantType<#indices> for #name {
+                type Type = #variant_types;
+            }
+        )*
+    )
+}
+
 fn impl_sub_error(error: &CompositeError) -> TokenStream {
     let CompositeError { name, variants, .. } = error;
 
+    if !error.inline.is_empty() {
+        return TokenStream::new();
+    }
+
     let froms = variants
         .iter()
         .map(|Variant { typ, .. }| quote!(::core::convert::From<#typ>));
@@ -61,7 +437,7 @@ fn impl_sub_error(error: &CompositeError) -> TokenStream {
         |Variant { name: variant, .. }| quote!(#name::#variant(e) => ::core::convert::From::from(e)),
     );
 
-    quote!(
+    let mut stream = quote!(
         #[automatically_derived]
         impl<T> ::throwing::SubError<T> for #name where T: #(#froms)+* {
             fn to_super_error(self) -> T {
@@ -70,18 +446,92 @@ fn impl_sub_error(error: &CompositeError) -> TokenStream {
                 }
             }
         }
+    );
+
+    // An empty enum would overlap with the impl above
+    if !variants.is_empty() {
+        let indices: Vec<_> = (0..variants.len())
+            .map(|i| format_ident!("__I{i}"))
+            .collect();
+
+        let contains = variants
+            .iter()
+            .zip(&indices)
+            .map(|(Variant { typ, .. }, index)| quote!(::throwing::one_of::Contains<#typ, #index>));
+
+        let arms = variants.iter().map(
+            |Variant { name: variant, .. }| quote!(#name::#variant(e) => ::throwing::OneOf::new(e)),
+        );
+
+        stream.extend(quote!(
+            #[automatically_derived]
+            impl<__U, #(#indices),*> ::throwing::__private::Upcast<
+                ::throwing::OneOf<__U>,
+                ::throwing::one_of::Subset<(#(#indices,)*)>,
+            > for #name
+            where
+                __U: #(#contains)+*
+            {
+                fn upcast(self) -> ::throwing::OneOf<__U> {
+                    match self {
+                        #(#arms),*
+                    }
+                }
+            }
+        ));
+    }
+
+    stream
+}
+
+fn impl_from_widened(error: &CompositeError) -> TokenStream {
+    let CompositeError { name, .. } = error;
+
+    quote!(
+        #[automatically_derived]
+        impl<__E> ::core::convert::From<::throwing::Widened<__E>> for #name
+        where
+            __E: ::throwing::SubError<#name>,
+        {
+            fn from(error: ::throwing::Widened<__E>) -> Self {
+                ::throwing::SubError::to_super_error(error.0)
+            }
+        }
     )
 }
 
 fn impl_display(error: &CompositeError) -> TokenStream {
-    let CompositeError { name, variants, .. } = error;
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
 
-    let body = if variants.is_empty() {
+    // Fields of inline variants are in scope of their message, so they must not shadow this
+    let f = Ident::new("f", Span::mixed_site());
+
+    let body = if variants.is_empty() && inline.is_empty() {
         quote!(match *self {})
     } else {
         let arms = variants
             .iter()
-            .map(|Variant { name: variant, .. }| quote!(#name::#variant(e) => ::core::fmt::Display::fmt(e, f)));
+            .map(|Variant { name: variant, .. }| quote!(#name::#variant(e) => ::core::fmt::Display::fmt(e, #f)))
+            .chain(inline.iter().map(
+                |InlineVariant {
+                     name: variant,
+                     fields,
+                     message,
+                     ..
+                 }| {
+                    let fields = fields.iter().flat_map(|f| &f.named).map(|f| &f.ident);
+
+                    quote!(
+                        #[allow(unused_variables)]
+                        #name::#variant { #(#fields,)* } => ::core::write!(#f, #message)
+                    )
+                },
+            ));
 
         quote!(
             match self {
@@ -93,7 +543,7 @@ fn impl_display(error: &CompositeError) -> TokenStream {
     quote!(
         #[automatically_derived]
         impl ::core::fmt::Display for #name {
-            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+            fn fmt(&self, #f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
                 #body
             }
         }
@@ -101,14 +551,29 @@ fn impl_display(error: &CompositeError) -> TokenStream {
 }
 
 fn impl_error(error: &CompositeError) -> TokenStream {
-    let CompositeError { name, variants, .. } = error;
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
 
-    let body = if variants.is_empty() {
+    let body = if variants.is_empty() && inline.is_empty() {
         quote!(match *self {})
     } else {
+        // The source of a variant with context is the error, not the context,
+        // and the source of a shared or recursive variant is the error inside the `Arc` or `Box`
         let arms = variants
             .iter()
-            .map(|Variant { name: variant, .. }| quote!(#name::#variant(e) => ::core::option::Option::Some(e)));
+            .map(|variant| {
+                let variant_name = &variant.name;
+                let error = held_error(name, variant);
+
+                quote!(#name::#variant_name(e) => ::core::option::Option::Some(#error))
+            })
+            .chain(inline.iter().map(
+                |InlineVariant { name: variant, .. }| quote!(#name::#variant { .. } => ::core::option::Option::None),
+            ));
 
         quote!(
             match self {
@@ -127,37 +592,726 @@ fn impl_error(error: &CompositeError) -> TokenStream {
     )
 }
 
+fn impl_throwing_error(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let names: Vec<String> = variants
+        .iter()
+        .map(|v| v.name.to_string())
+        .chain(inline.iter().map(|v| v.name.to_string()))
+        .collect();
+
+    let (variant_name, type_name, nested) = if variants.is_empty() && inline.is_empty() {
+        (
+            quote!(match *self {}),
+            quote!(match *self {}),
+            quote!(match *self {}),
+        )
+    } else {
+        let name_arms = variants
+            .iter()
+            .map(|Variant { name: variant, .. }| quote!(#name::#variant(_)))
+            .chain(
+                inline
+                    .iter()
+                    .map(|InlineVariant { name: variant, .. }| quote!(#name::#variant { .. })),
+            )
+            .zip(&names)
+            .map(|(pattern, string)| quote!(#pattern => #string));
+
+        let type_arms = variants.iter().map(|variant| {
+            let variant_name = &variant.name;
+            let inner = held_error(name, variant);
+
+            quote!(#name::#variant_name(e) => ::core::option::Option::Some(
+                ::throwing::__private::type_name_of(#inner)
+            ))
+        });
+
+        let nested_arms = variants.iter().map(|variant| {
+            let variant_name = &variant.name;
+            let inner = held_error(name, variant);
+
+            quote!(#name::#variant_name(e) => (&::throwing::__private::Nested(#inner)).nested())
+        });
+
+        let inline_arms: Vec<_> = inline
+            .iter()
+            .map(|InlineVariant { name: variant, .. }| {
+                quote!(#name::#variant { .. } => ::core::option::Option::None)
+            })
+            .collect();
+
+        (
+            quote!(match self { #(#name_arms),* }),
+            quote!(match self { #(#type_arms,)* #(#inline_arms),* }),
+            quote!(
+                #[allow(unused_imports)]
+                use ::throwing::__private::{HasNested as _, NoNested as _};
+
+                match self { #(#nested_arms,)* #(#inline_arms),* }
+            ),
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::throwing::ThrowingError for #name {
+            fn variant_name(&self) -> &'static str {
+                #variant_name
+            }
+
+            fn inner(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                ::std::error::Error::source(self)
+            }
+
+            fn variant_type_name(&self) -> ::core::option::Option<&'static str> {
+                #type_name
+            }
+
+            fn nested(&self) -> ::core::option::Option<&dyn ::throwing::ThrowingError> {
+                #nested
+            }
+
+            fn variants() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+        }
+    )
+}
+
+fn impl_error_metadata(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let option = |value: Option<TokenStream>| match value {
+        Some(value) => quote!(::core::option::Option::Some(#value)),
+        None => quote!(::core::option::Option::None),
+    };
+
+    let variants = variants.iter().map(
+        |Variant {
+             typ,
+             written,
+             context,
+             name: variant,
+             annotations,
+         }| {
+            // Types of inferred variants are only known by the error of the called function
+            let type_name = match inferred_variant(typ) {
+                Some((error, index)) => quote!(
+                    <#error as ::throwing::ErrorMetadata>::VARIANTS[#index].type_name
+                ),
+                None => option(Some(type_to_string(written).to_token_stream())),
+            };
+
+            let span = context.as_deref().unwrap_or(typ).span();
+            (variant, type_name, None, span, annotations)
+        },
+    );
+
+    let inline = inline.iter().map(
+        |InlineVariant {
+             name: variant,
+             message,
+             annotations,
+             ..
+         }| {
+            let message = Some(message.value().to_token_stream());
+            (variant, option(None), message, variant.span(), annotations)
+        },
+    );
+
+    let infos = variants
+        .chain(inline)
+        .map(|(variant, type_name, message, span, annotations)| {
+            let variant = variant.to_string();
+            let message = option(message);
+            let code = option(
+                annotations
+                    .code
+                    .as_ref()
+                    .map(|c| c.to_string().to_token_stream()),
+            );
+            let status = option(annotations.status.map(|s| s.to_token_stream()));
+
+            // The location macros report the line of the span they are invoked with
+            let file = quote_spanned!(span=> ::core::file!());
+            let line = quote_spanned!(span=> ::core::line!());
+
+            quote!(
+                ::throwing::VariantInfo {
+                    name: #variant,
+                    type_name: #type_name,
+                    message: #message,
+                    file: #file,
+                    line: #line,
+                    code: #code,
+                    status: #status,
+                }
+            )
+        });
+
+    quote!(
+        #[automatically_derived]
+        impl ::throwing::ErrorMetadata for #name {
+            const VARIANTS: &'static [::throwing::VariantInfo] = &[#(#infos),*];
+        }
+    )
+}
+
+/// Returns the error and variant index of a variant type added by the `infer` option.
+fn inferred_variant(typ: &Type) -> Option<(&Type, &GenericArgument)> {
+    let Type::Path(TypePath {
+        qself: Some(qself),
+        path,
+    }) = typ
+    else {
+        return None;
+    };
+
+    let trait_segment = path.segments.get(qself.position.checked_sub(1)?)?;
+
+    if trait_segment.ident != "VariantType" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &trait_segment.arguments else {
+        return None;
+    };
+
+    Some((&qself.ty, args.args.first()?))
+}
+
+fn impl_inspect(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let body = if variants.is_empty() && inline.is_empty() {
+        quote!(match *self {})
+    } else {
+        let arms = variants
+            .iter()
+            .map(|variant| {
+                let variant_name = &variant.name;
+                let error = held_error(name, variant);
+
+                quote!(#name::#variant_name(e) => <dyn ::std::error::Error>::is::<T>(#error))
+            })
+            .chain(inline.iter().map(
+                |InlineVariant { name: variant, .. }| quote!(#name::#variant { .. } => false),
+            ));
+
+        quote!(
+            match self {
+                #(#arms),*
+            }
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::throwing::testing::Inspect for #name {
+            fn holds<T: ::std::error::Error + 'static>(&self) -> bool {
+                #body
+            }
+        }
+    )
+}
+
+/// Compares errors by their variant and their message,
+/// since the errors they contain usually don't implement `PartialEq`.
+fn impl_partial_eq(error: &CompositeError) -> TokenStream {
+    let CompositeError { name, .. } = error;
+
+    quote!(
+        #[automatically_derived]
+        impl ::core::cmp::PartialEq for #name {
+            fn eq(&self, other: &Self) -> bool {
+                ::core::mem::discriminant(self) == ::core::mem::discriminant(other)
+                    && ::std::string::ToString::to_string(self)
+                        == ::std::string::ToString::to_string(other)
+            }
+        }
+    )
+}
+
+fn impl_exit_code_error(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let body = if variants.is_empty() && inline.is_empty() {
+        quote!(match *self {})
+    } else {
+        let arms = variants
+            .iter()
+            .map(|variant| (&variant.name, &variant.annotations))
+            .chain(
+                inline
+                    .iter()
+                    .map(|variant| (&variant.name, &variant.annotations)),
+            )
+            .map(|(variant_name, annotations)| {
+                let code = annotations.exit.unwrap_or(1);
+
+                quote!(#name::#variant_name { .. } => #code)
+            });
+
+        quote!(
+            match self {
+                #(#arms),*
+            }
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::throwing::ExitCodeError for #name {
+            fn exit_code(&self) -> u8 {
+                #body
+            }
+        }
+    )
+}
+
+fn impl_io_error(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let into_body = if variants.is_empty() && inline.is_empty() {
+        quote!(match error {})
+    } else {
+        let io_kind = |kind: &Option<Ident>| {
+            kind.clone()
+                .unwrap_or_else(|| Ident::new("Other", Span::call_site()))
+        };
+
+        let arms = variants.iter().map(|variant| {
+            let variant_name = &variant.name;
+            let kind = io_kind(&variant.annotations.io_kind);
+
+            quote!(
+                #name::#variant_name(e) => {
+                    match ::throwing::__private::cast::<_, ::std::io::Error>(e) {
+                        ::core::result::Result::Ok(e) => e,
+                        ::core::result::Result::Err(e) => ::std::io::Error::new(
+                            ::std::io::ErrorKind::#kind,
+                            #name::#variant_name(e),
+                        ),
+                    }
+                }
+            )
+        });
+
+        // Inline variants are always wrapped, since they don't contain another error
+        let inline_arms = inline.iter().map(|variant| {
+            let variant_name = &variant.name;
+            let kind = io_kind(&variant.annotations.io_kind);
+
+            quote!(
+                error @ #name::#variant_name { .. } => {
+                    ::std::io::Error::new(::std::io::ErrorKind::#kind, error)
+                }
+            )
+        });
+
+        quote!(
+            match error {
+                #(#arms,)*
+                #(#inline_arms),*
+            }
+        )
+    };
+
+    let recoveries = variants.iter().map(|Variant { name: variant, typ, .. }| {
+        quote!(
+            let error = match ::throwing::__private::cast::<_, #typ>(error) {
+                ::core::result::Result::Ok(e) => return ::core::result::Result::Ok(#name::#variant(e)),
+                ::core::result::Result::Err(e) => e,
+            };
+        )
+    });
+
+    quote!(
+        #[automatically_derived]
+        impl ::core::convert::From<#name> for ::std::io::Error {
+            fn from(error: #name) -> ::std::io::Error {
+                #into_body
+            }
+        }
+
+        #[automatically_derived]
+        impl #name {
+            /// Recovers an error previously converted into an [`std::io::Error`].
+            ///
+            /// If the error can't be recovered, it's returned unchanged.
+            pub fn try_from_io_error(
+                error: ::std::io::Error,
+            ) -> ::core::result::Result<#name, ::std::io::Error> {
+                let error = match ::throwing::__private::downcast_io_error::<#name>(error) {
+                    ::core::result::Result::Ok(e) => return ::core::result::Result::Ok(e),
+                    ::core::result::Result::Err(e) => e,
+                };
+
+                #(#recoveries)*
+
+                ::core::result::Result::Err(error)
+            }
+        }
+    )
+}
+
 pub fn error_definition(error: CompositeError) -> TokenStream {
     let mut stream = error_enum(&error);
 
+    // Errors with shared variants are meant to be cloned,
+    // so the other variants are expected to implement `Clone` as well
+    if error.options.shared.is_some() || error.variants.iter().any(|v| v.annotations.shared) {
+        stream.extend(impl_clone(&error));
+    }
+
     for variant in &error.variants {
         stream.extend(impl_from_variant(&error, variant));
     }
 
+    if !error.inline.is_empty() {
+        stream.extend(impl_constructors(&error));
+    }
+
+    stream.extend(impl_combinators(&error));
+
     for typ in &error.composed {
         stream.extend(impl_from_composed(&error, typ));
     }
 
     stream.extend(impl_sub_error(&error));
+    stream.extend(impl_variants(&error));
+    stream.extend(impl_from_widened(&error));
     stream.extend(impl_display(&error));
     stream.extend(impl_error(&error));
+    stream.extend(impl_exit_code_error(&error));
+    stream.extend(impl_inspect(&error));
+    stream.extend(impl_throwing_error(&error));
+    stream.extend(impl_error_metadata(&error));
+
+    if error.options.io_error.is_some() {
+        stream.extend(impl_io_error(&error));
+    }
+
+    if error.options.partial_eq.is_some() {
+        stream.extend(impl_partial_eq(&error));
+    }
+
+    interop_impls(&error, &mut stream);
 
     stream
 }
 
-fn wrap_return_with_result(ret: ReturnType, error: Ident) -> ReturnType {
+fn result_type(typ: Type, error: Type, result: Option<&Path>) -> Type {
+    match result {
+        Some(result) => parse_quote!(#result<#typ, #error>),
+        None => parse_quote!(::core::result::Result<#typ, #error>),
+    }
+}
+
+fn wrap_return_with_result(ret: ReturnType, error: Type, result: Option<&Path>) -> ReturnType {
     let (arrow, typ) = match ret {
         ReturnType::Default => (Default::default(), parse_quote!(())),
         ReturnType::Type(arrow, typ) => (arrow, *typ),
     };
 
-    let typ = parse_quote!(::core::result::Result<#typ, #error>);
+    ReturnType::Type(arrow, Box::new(result_type(typ, error, result)))
+}
+
+/// Returns the type wrapped by an `Option<T>` or `Poll<T>`.
+fn nested_type(typ: &mut Type) -> Option<&mut Type> {
+    let Type::Path(path) = typ else {
+        return None;
+    };
+
+    if path.qself.is_some() {
+        return None;
+    }
+
+    let last = path.path.segments.last_mut()?;
+
+    if last.ident != "Option" && last.ident != "Poll" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &mut last.arguments else {
+        return None;
+    };
+
+    if args.args.len() != 1 {
+        return None;
+    }
+
+    match args.args.first_mut()? {
+        GenericArgument::Type(typ) => Some(typ),
+        _ => None,
+    }
+}
+
+fn returns_option(ret: &ReturnType) -> bool {
+    let ReturnType::Type(_, typ) = ret else {
+        return false;
+    };
+
+    let Type::Path(path) = &**typ else {
+        return false;
+    };
+
+    path.path
+        .segments
+        .last()
+        .is_some_and(|s| s.ident == "Option")
+}
+
+fn wrap_nested_return_with_result(
+    ret: ReturnType,
+    error: Type,
+    result: Option<&Path>,
+    nested: &Ident,
+) -> Result<ReturnType, Error> {
+    let message = "the `nested` option requires the function to return `Option<T>` or `Poll<T>`";
+
+    let ReturnType::Type(arrow, mut typ) = ret else {
+        return Err(Error::new_spanned(nested, message));
+    };
+
+    let Some(inner) = nested_type(&mut typ) else {
+        return Err(Error::new_spanned(typ, message));
+    };
+
+    *inner = result_type(inner.clone(), error, result);
+
+    Ok(ReturnType::Type(arrow, typ))
+}
+
+pub fn patch_function(
+    mut function: ItemFn,
+    error: Path,
+    options: &Options,
+) -> Result<ItemFn, Error> {
+    let output = function.sig.output;
+    let error = parse_quote!(#error);
+    let result = options.result.as_ref();
+
+    if options.nested.is_some() && returns_option(&output) {
+        convert_try_in_option(&mut function.block);
+    }
 
-    ReturnType::Type(arrow, Box::new(typ))
+    function.sig.output = match &options.nested {
+        Some(nested) => wrap_nested_return_with_result(output, error, result, nested)?,
+        None => wrap_return_with_result(output, error, result),
+    };
+
+    if let Some(auto_ok) = &options.auto_ok {
+        if options.nested.is_some() {
+            return Err(Error::new_spanned(
+                auto_ok,
+                "the `auto_ok` option cannot be combined with `nested`",
+            ));
+        }
+
+        wrap_in_ok(&mut function.block);
+    }
+
+    Ok(function)
 }
 
-pub fn patch_function(mut function: ItemFn, error: Ident) -> ItemFn {
-    function.sig.output = wrap_return_with_result(function.sig.output, error);
+/// Rewrites uses of `throw!()` in the function to check that the error can be converted.
+///
+/// The check uses a trait local to the function, so that the diagnostic can list
+/// the types the error can be created from.
+pub fn convert_function_throws(function: &mut ItemFn, error: &CompositeError, path: &Path) {
+    let check = Ident::new("Throw", Span::mixed_site());
+
+    if !convert_throw(&mut function.block, &quote!(#check::throw)) {
+        return;
+    }
+
+    let name = &error.name;
+
+    // The function is outside of the module of the error if the `module` option is used
+    let variants: Vec<Type> = error
+        .variants
+        .iter()
+        .map(|Variant { typ, .. }| {
+            let mut typ = typ.clone();
+            qualify_error_name(&mut typ, name, path);
+            typ
+        })
+        .collect();
+
+    let types: Vec<&Type> = variants.iter().chain(&error.composed).collect();
+
+    // Types of inferred variants are only known by the variant of the called function's error
+    let descriptions = variants
+        .iter()
+        .zip(&error.variants)
+        .map(|(typ, Variant { name, .. })| match typ {
+            Type::Path(TypePath {
+                qself: Some(qself), ..
+            }) => format!("the type of `{}::{name}`", type_to_string(&qself.ty)),
+            typ => format!("`{}`", type_to_string(typ)),
+        });
+
+    let descriptions: Vec<String> = descriptions
+        .chain(
+            error
+                .composed
+                .iter()
+                .map(|typ| format!("`{}`", type_to_string(typ))),
+        )
+        .collect();
+
+    let message = format!("`{{Self}}` cannot be thrown as `{name}`");
+    let label = format!("`{name}` cannot be created from this");
+
+    let note = if descriptions.is_empty() {
+        format!("`{name}` has no variants containing other errors")
+    } else {
+        // Braces have to be escaped, since they are used for placeholders
+        let descriptions = descriptions
+            .join(", ")
+            .replace('{', "{{")
+            .replace('}', "}}");
+        format!("`{name}` can be created from {descriptions}")
+    };
+
+    let definition: Block = parse_quote!({
+        #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+        trait #check {
+            fn throw(self) -> #path;
+        }
+
+        impl #check for #path {
+            fn throw(self) -> #path {
+                self
+            }
+        }
+
+        #(
+            impl #check for #types {
+                fn throw(self) -> #path {
+                    ::core::convert::From::from(self)
+                }
+            }
+        )*
+
+        impl<T> #check for ::throwing::Widened<T>
+        where
+            T: ::throwing::SubError<#path>,
+        {
+            fn throw(self) -> #path {
+                ::core::convert::From::from(self)
+            }
+        }
+    });
+
+    function.block.stmts.splice(0..0, definition.stmts);
+}
+
+pub fn error_module(mut error: CompositeError, module: &Ident) -> TokenStream {
+    let visibility = std::mem::replace(&mut error.visibility, parse_quote!(pub));
+
+    for Variant { typ, .. } in &mut error.variants {
+        nest_super_paths(typ);
+    }
+
+    for typ in &mut error.composed {
+        nest_super_paths(typ);
+    }
+
+    let definition = error_definition(error);
+
+    quote!(
+        #visibility mod #module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #definition
+        }
+    )
+}
+
+pub fn try_block_closure(error: CompositeError, block: Block) -> TokenStream {
+    let name = error.name.clone();
+    let definition = error_definition(error);
+
+    quote!({
+        #definition
+
+        (|| -> ::core::result::Result<_, #name> #block)()
+    })
+}
+
+pub fn patch_anon_function(mut function: ItemFn, types: &[Type]) -> ItemFn {
+    let error = parse_quote!(::throwing::OneOf<(#(#types,)*)>);
+    function.sig.output = wrap_return_with_result(function.sig.output, error, None);
+    convert_try_to_union(&mut function.block);
+    convert_throw(
+        &mut function.block,
+        &quote!(::throwing::__private::IntoOneOf::into_one_of),
+    );
 
     function
 }
+
+pub fn report_wrapper(function: ItemFn) -> Result<ItemFn, Error> {
+    if let Some(input) = function.sig.inputs.first() {
+        return Err(Error::new_spanned(
+            input,
+            "the `report` option can only be used on functions without parameters",
+        ));
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+
+    let mut outer_sig = sig.clone();
+    outer_sig.output = parse_quote!(-> ::std::process::ExitCode);
+
+    let ident = &sig.ident;
+    let call = if sig.asyncness.is_some() {
+        quote!(#ident().await)
+    } else {
+        quote!(#ident())
+    };
+
+    Ok(parse_quote!(
+        #(#attrs)*
+        #vis #outer_sig {
+            #sig #block
+
+            ::throwing::report(#call)
+        }
+    ))
+}
@@ -0,0 +1,397 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{
+    braced, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+    parse2, parse_quote,
+    visit::{self, Visit},
+    Error, Expr, Ident, Item, ItemFn, Path, Result, Type, Visibility,
+};
+
+use crate::{
+    attributes::{ThrowsArgs, VariantArg},
+    names::{type_to_string, type_to_variant},
+    type_key,
+    types::Naming,
+    FunctionError,
+};
+
+/// A function whose error type is needed to infer the variants of another error.
+struct Callee {
+    /// The path to the module containing the function, including the trailing `::`.
+    prefix: TokenStream,
+    ident: Ident,
+}
+
+impl Callee {
+    fn companion(&self) -> TokenStream {
+        let Callee { prefix, ident } = self;
+        let name = companion_name(ident);
+
+        quote!(#prefix #name)
+    }
+}
+
+/// Finds calls to other functions that are followed by the `?` operator.
+///
+/// Like with the rewriting of `?` in anonymous mode, closures, async blocks
+/// and nested items are skipped.
+struct FindCallees {
+    callees: Vec<Callee>,
+    unknown: Vec<Span>,
+}
+
+impl<'ast> Visit<'ast> for FindCallees {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::Try(try_expr) => {
+                visit::visit_expr(self, &try_expr.expr);
+
+                match inferable_callee(&try_expr.expr) {
+                    Some(callee) => {
+                        let key = callee.companion().to_string();
+
+                        if !self
+                            .callees
+                            .iter()
+                            .any(|c| c.companion().to_string() == key)
+                        {
+                            self.callees.push(callee);
+                        }
+                    }
+                    None => self.unknown.push(try_expr.question_token.span),
+                }
+            }
+            _ => visit::visit_expr(self, expr),
+        }
+    }
+
+    fn visit_item(&mut self, _item: &'ast Item) {}
+}
+
+/// Returns the callee if the expression is a call to a function with a simple path,
+/// either a single identifier or a path starting with `crate`, `self` or `super`.
+fn inferable_callee(expr: &Expr) -> Option<Callee> {
+    let Expr::Call(call) = expr else {
+        return None;
+    };
+
+    let Expr::Path(path) = &*call.func else {
+        return None;
+    };
+
+    if path.qself.is_some() || path.path.leading_colon.is_some() {
+        return None;
+    }
+
+    let segments: Vec<&Ident> = path.path.segments.iter().map(|s| &s.ident).collect();
+    let (ident, modules) = segments.split_last()?;
+
+    let relative = modules.first().map_or(true, |first| {
+        *first == "crate" || *first == "self" || *first == "super"
+    });
+
+    if !relative {
+        return None;
+    }
+
+    Some(Callee {
+        prefix: quote!(#(#modules::)*),
+        ident: (*ident).clone(),
+    })
+}
+
+fn companion_name(ident: &Ident) -> Ident {
+    format_ident!("__throwing_{}", ident, span = ident.span())
+}
+
+/// Starts inferring the variants of the error returned by the function.
+pub fn infer_throws(
+    attributes: TokenStream,
+    args: &ThrowsArgs,
+    function: ItemFn,
+) -> Result<TokenStream> {
+    let mut finder = FindCallees {
+        callees: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    finder.visit_block(&function.block);
+
+    if args.variants.is_empty() {
+        if let Some(span) = finder.unknown.first() {
+            return Err(Error::new(
+                *span,
+                "cannot infer the error type of this expression, list it explicitly in #[throws(...)]",
+            ));
+        }
+    }
+
+    let next = finder.callees.iter().map(|Callee { prefix, ident }| {
+        let name = companion_name(ident);
+        quote!((#prefix) #name)
+    });
+
+    Ok(quote!(::throwing::__private::throws_infer! {
+        [#(#next)*] [] (#attributes) #function
+    }))
+}
+
+/// The error type of a callee, as reported by its companion macro.
+struct Inferred {
+    prefix: TokenStream,
+    error: Path,
+    names: Vec<Ident>,
+}
+
+impl Parse for Inferred {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        braced!(content in input);
+
+        let prefix;
+        parenthesized!(prefix in content);
+
+        let error;
+        parenthesized!(error in content);
+
+        let names;
+        bracketed!(names in content);
+
+        let mut variants = Vec::new();
+        while !names.is_empty() {
+            variants.push(names.parse()?);
+        }
+
+        Ok(Inferred {
+            prefix: prefix.parse()?,
+            error: error.parse()?,
+            names: variants,
+        })
+    }
+}
+
+pub struct InferInput {
+    next: Vec<(TokenStream, Ident)>,
+    inferred: Vec<Inferred>,
+    attributes: TokenStream,
+    function: ItemFn,
+}
+
+impl Parse for InferInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        bracketed!(content in input);
+
+        let mut next = Vec::new();
+        while !content.is_empty() {
+            let prefix;
+            parenthesized!(prefix in content);
+            next.push((prefix.parse()?, content.parse()?));
+        }
+
+        let content;
+        bracketed!(content in input);
+
+        let mut inferred = Vec::new();
+        while !content.is_empty() {
+            inferred.push(content.parse()?);
+        }
+
+        let attributes;
+        parenthesized!(attributes in input);
+
+        Ok(InferInput {
+            next,
+            inferred,
+            attributes: attributes.parse()?,
+            function: input.parse()?,
+        })
+    }
+}
+
+/// Asks the next callee for its error type, or generates the function once all are known.
+pub fn continue_inference(input: InferInput) -> Result<TokenStream> {
+    let InferInput {
+        mut next,
+        inferred,
+        attributes,
+        function,
+    } = input;
+
+    if !next.is_empty() {
+        let (prefix, name) = next.remove(0);
+
+        let next = next.iter().map(|(prefix, name)| quote!((#prefix) #name));
+        let inferred = inferred.iter().map(
+            |Inferred {
+                 prefix,
+                 error,
+                 names,
+             }| quote!({ (#prefix) (#error) [#(#names)*] }),
+        );
+
+        return Ok(quote!(
+            #prefix #name! {
+                (#prefix) [#(#next)*] [#(#inferred)*] (#attributes) #function
+            }
+        ));
+    }
+
+    let mut args: ThrowsArgs = parse2(attributes)?;
+    let infer = args
+        .options
+        .infer
+        .take()
+        .expect("inference should be enabled");
+
+    let naming = args
+        .options
+        .naming
+        .as_ref()
+        .map_or_else(Naming::default, |(_, n)| *n);
+
+    let mut names: Vec<Ident> = args
+        .variants
+        .iter()
+        .filter_map(|arg| match arg {
+            VariantArg::Variant {
+                typ, context, name, ..
+            } => name
+                .clone()
+                .or_else(|| type_to_variant(context.as_deref().unwrap_or(typ), naming)),
+            VariantArg::Inline { name, .. } => Some(name.clone()),
+            VariantArg::Composed { .. } => None,
+        })
+        .collect();
+
+    let inline: Vec<Ident> = args
+        .variants
+        .iter()
+        .filter_map(|arg| match arg {
+            VariantArg::Inline { name, .. } => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+
+    // Variants with the same name as an existing one are checked to have the same type
+    let mut checks = Vec::new();
+
+    let mut composed: Vec<String> = args
+        .variants
+        .iter()
+        .filter_map(|arg| match arg {
+            VariantArg::Variant { typ, .. } | VariantArg::Composed { typ } => Some(type_key(typ)),
+            VariantArg::Inline { .. } => None,
+        })
+        .collect();
+
+    for Inferred {
+        prefix,
+        error,
+        names: variants,
+    } in inferred
+    {
+        let error: Type = parse_quote!(#prefix #error);
+
+        for (index, name) in variants.into_iter().enumerate() {
+            let typ: Type = parse_quote!(
+                <#error as ::throwing::__private::VariantType<#index>>::Type
+            );
+
+            if inline.contains(&name) {
+                return Err(Error::new_spanned(
+                    &infer,
+                    format!(
+                        "the inferred variant `{name}` of `{}` has the same name as an inline variant",
+                        type_to_string(&error),
+                    ),
+                ));
+            }
+
+            if names.contains(&name) {
+                checks.push((Ident::new(&name.to_string(), infer.span()), typ));
+                continue;
+            }
+
+            args.variants.push(VariantArg::Variant {
+                typ,
+                context: None,
+                name: Some(name.clone()),
+                annotations: None,
+            });
+
+            names.push(name);
+        }
+
+        let key = type_key(&error);
+
+        if !composed.contains(&key) {
+            args.variants.push(VariantArg::Composed { typ: error });
+            composed.push(key);
+        }
+    }
+
+    crate::expand_throws(args, function, &checks)
+}
+
+/// Checks that inferred variants have the same type as the existing variants with their names.
+pub fn variant_type_checks(error: &Path, checks: &[(Ident, Type)]) -> TokenStream {
+    if checks.is_empty() {
+        return TokenStream::new();
+    }
+
+    let checks = checks.iter().map(|(variant, typ)| {
+        quote_spanned!(variant.span()=>
+            ::throwing::__private::check_variant_type::<#typ, _, _>(#error::#variant);
+        )
+    });
+
+    quote!(
+        const _: () = {
+            fn check() {
+                #(#checks)*
+            }
+        };
+    )
+}
+
+/// Generates a hidden macro that tells callers which variants the error type has,
+/// if the function has the `inferable` option.
+///
+/// The macro is named after the function, so that callers can find it
+/// without knowing the name of the error type.
+pub fn companion_macro(function: &ItemFn, error: &FunctionError) -> TokenStream {
+    if error.error.options.inferable.is_none() {
+        return TokenStream::new();
+    }
+
+    let name = companion_name(&function.sig.ident);
+    let path = error.path();
+    let variants = error.error.variants.iter().map(|v| &v.name);
+
+    // Macros defined by `macro_rules!` can't be exported from the crate with `use`
+    let visibility = match &function.vis {
+        Visibility::Public(_) => quote!(pub(crate)),
+        visibility => visibility.to_token_stream(),
+    };
+
+    quote!(
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #name {
+            (($($prefix:tt)*) [$($next:tt)*] [$($inferred:tt)*] $($rest:tt)*) => {
+                ::throwing::__private::throws_infer! {
+                    [$($next)*]
+                    [$($inferred)* { ($($prefix)*) (#path) [#(#variants)*] }]
+                    $($rest)*
+                }
+            };
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        #visibility use #name;
+    )
+}
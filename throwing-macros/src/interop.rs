@@ -0,0 +1,307 @@
+use crate::types::{CompositeError, InlineVariant, Variant};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+fn impl_serialize(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+    let type_name = name.to_string();
+
+    let body = if variants.is_empty() && inline.is_empty() {
+        quote!(
+            let _ = serializer;
+            match *self {}
+        )
+    } else {
+        let arms = variants.iter().map(|variant| {
+            let Variant {
+                name: variant_name,
+                annotations,
+                ..
+            } = variant;
+
+            let kind = variant_name.to_string();
+            let mut fields = vec![
+                quote!(state.serialize_field("kind", #kind)?;),
+                quote!(state.serialize_field("message", &::throwing::__private::Message(e))?;),
+            ];
+
+            if let Some(code) = &annotations.code {
+                let code = code.to_string();
+                fields.push(quote!(state.serialize_field("code", #code)?;));
+            }
+
+            if annotations.serde {
+                fields.push(quote!(state.serialize_field("error", e)?;));
+            }
+
+            let len = fields.len();
+
+            quote!(
+                #name::#variant_name(e) => {
+                    let mut state = serializer.serialize_struct(#type_name, #len)?;
+                    #(#fields)*
+                    state.end()
+                }
+            )
+        });
+
+        let inline_arms = inline.iter().map(|variant| {
+            let InlineVariant {
+                name: variant_name,
+                annotations,
+                ..
+            } = variant;
+
+            let kind = variant_name.to_string();
+            let mut fields = vec![
+                quote!(state.serialize_field("kind", #kind)?;),
+                quote!(state.serialize_field("message", &::throwing::__private::Message(self))?;),
+            ];
+
+            if let Some(code) = &annotations.code {
+                let code = code.to_string();
+                fields.push(quote!(state.serialize_field("code", #code)?;));
+            }
+
+            let len = fields.len();
+
+            quote!(
+                #name::#variant_name { .. } => {
+                    let mut state = serializer.serialize_struct(#type_name, #len)?;
+                    #(#fields)*
+                    state.end()
+                }
+            )
+        });
+
+        quote!(
+            use ::throwing::__private::serde::ser::SerializeStruct;
+
+            match self {
+                #(#arms,)*
+                #(#inline_arms),*
+            }
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::throwing::__private::serde::Serialize for #name {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: ::throwing::__private::serde::Serializer,
+            {
+                #body
+            }
+        }
+    )
+}
+
+fn impl_deserialize(error: &CompositeError) -> Option<TokenStream> {
+    let CompositeError { name, variants, .. } = error;
+
+    let variants: Vec<&Variant> = variants.iter().filter(|v| v.annotations.serde).collect();
+
+    if variants.is_empty() {
+        return None;
+    }
+
+    let repr_variants = variants
+        .iter()
+        .map(|Variant { name, typ, .. }| quote!(#name { error: #typ }));
+
+    let arms = variants.iter().map(|Variant { name: variant, .. }| {
+        quote!(Repr::#variant { error } => ::core::result::Result::Ok(#name::#variant(error)))
+    });
+
+    Some(quote!(
+        #[automatically_derived]
+        impl<'de> ::throwing::__private::serde::Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: ::throwing::__private::serde::Deserializer<'de>,
+            {
+                #[derive(::throwing::__private::serde::Deserialize)]
+                #[serde(crate = "::throwing::__private::serde", tag = "kind")]
+                enum Repr {
+                    #(#repr_variants),*
+                }
+
+                match <Repr as ::throwing::__private::serde::Deserialize>::deserialize(deserializer)? {
+                    #(#arms),*
+                }
+            }
+        }
+    ))
+}
+
+fn impl_into_response(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        ..
+    } = error;
+
+    let body = if variants.is_empty() && inline.is_empty() {
+        quote!(match self {})
+    } else {
+        let arms = variants.iter().map(|variant| {
+            let Variant {
+                name: variant_name,
+                annotations,
+                ..
+            } = variant;
+
+            if annotations.response {
+                return quote!(
+                    #name::#variant_name(e) => {
+                        ::throwing::__private::axum_core::response::IntoResponse::into_response(e)
+                    }
+                );
+            }
+
+            let kind = variant_name.to_string();
+            let code = match &annotations.code {
+                Some(code) => {
+                    let code = code.to_string();
+                    quote!(::core::option::Option::Some(#code))
+                }
+                None => quote!(::core::option::Option::None),
+            };
+
+            // Without a status, variant types implementing `IntoResponse` are used as they are
+            let Some(status) = annotations.status else {
+                return quote!(
+                    #name::#variant_name(e) => {
+                        #[allow(unused_imports)]
+                        use ::throwing::__private::{DefaultResponse as _, DelegateResponse as _};
+
+                        ::throwing::__private::Respond(e).respond(#kind, #code)
+                    }
+                );
+            };
+
+            quote!(
+                #name::#variant_name(e) => {
+                    ::throwing::__private::problem_details(#status, #kind, #code, &e)
+                }
+            )
+        });
+
+        let inline_arms = inline.iter().map(|variant| {
+            let InlineVariant {
+                name: variant_name,
+                annotations,
+                ..
+            } = variant;
+
+            let status = annotations.status.unwrap_or(500);
+            let kind = variant_name.to_string();
+            let code = match &annotations.code {
+                Some(code) => {
+                    let code = code.to_string();
+                    quote!(::core::option::Option::Some(#code))
+                }
+                None => quote!(::core::option::Option::None),
+            };
+
+            quote!(
+                #name::#variant_name { .. } => {
+                    ::throwing::__private::problem_details(#status, #kind, #code, &self)
+                }
+            )
+        });
+
+        quote!(
+            match self {
+                #(#arms,)*
+                #(#inline_arms),*
+            }
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::throwing::__private::axum_core::response::IntoResponse for #name {
+            fn into_response(self) -> ::throwing::__private::axum_core::response::Response {
+                #body
+            }
+        }
+    )
+}
+
+fn impl_into_status(error: &CompositeError) -> TokenStream {
+    let CompositeError {
+        name,
+        variants,
+        inline,
+        options,
+        ..
+    } = error;
+
+    let body = if variants.is_empty() && inline.is_empty() {
+        quote!(match error {})
+    } else {
+        let variants = variants
+            .iter()
+            .map(|variant| (&variant.name, &variant.annotations))
+            .chain(
+                inline
+                    .iter()
+                    .map(|variant| (&variant.name, &variant.annotations)),
+            );
+
+        let arms = variants.map(|(variant_name, annotations)| {
+            let kind = variant_name.to_string();
+            let code = match &annotations.grpc {
+                Some(code) => quote!(::throwing::__private::tonic::Code::#code),
+                None => quote!(::throwing::__private::tonic::Code::Internal),
+            };
+
+            quote!(#name::#variant_name { .. } => (#code, #kind))
+        });
+
+        let metadata = match &options.metadata {
+            Some(key) => quote!(::core::option::Option::Some(#key)),
+            None => quote!(::core::option::Option::None),
+        };
+
+        quote!(
+            let (code, kind) = match &error {
+                #(#arms),*
+            };
+
+            ::throwing::__private::grpc_status(code, kind, #metadata, &error)
+        )
+    };
+
+    quote!(
+        #[automatically_derived]
+        impl ::core::convert::From<#name> for ::throwing::__private::tonic::Status {
+            fn from(error: #name) -> ::throwing::__private::tonic::Status {
+                #body
+            }
+        }
+    )
+}
+
+pub fn interop_impls(error: &CompositeError, stream: &mut TokenStream) {
+    if cfg!(feature = "serde") {
+        stream.extend(impl_serialize(error));
+        stream.extend(impl_deserialize(error));
+    }
+
+    if cfg!(feature = "axum") {
+        stream.extend(impl_into_response(error));
+    }
+
+    if cfg!(feature = "tonic") {
+        stream.extend(impl_into_status(error));
+    }
+}
@@ -3,37 +3,94 @@
 //! The recommended way to use these macros is through the main crate,
 //! since it's required for the macros to function anyway.
 
-use attributes::{DefineErrorArgs, ThrowsArgs, VariantArg, VariantArgs};
-use codegen::{error_definition, patch_function};
-use names::{fn_name_to_error, type_to_variant};
+use attributes::{
+    DefineErrorArgs, ThrowsArgs, ThrowsModuleArgs, TryBlockArgs, VariantArg, VariantArgs,
+};
+use codegen::{
+    convert_function_throws, error_definition, error_module, patch_anon_function, patch_function,
+    report_wrapper, try_block_closure,
+};
+use infer::{companion_macro, continue_inference, infer_throws, variant_type_checks, InferInput};
+use names::{fn_name_to_error, type_to_variant, variant_to_method};
+use palette::expand_throws_module;
 use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::ToTokens;
-use syn::{parse_macro_input, Error, Item, Type};
-use types::{CompositeError, Variant};
+use syn::{
+    parse_macro_input, parse_quote, parse_quote_spanned, spanned::Spanned, Error, Ident, Item,
+    ItemFn, LitStr, Path, Signature, Type, Visibility,
+};
+use types::{CompositeError, InlineVariant, Naming, Options, Variant};
 
 mod attributes;
 mod codegen;
+mod infer;
+mod interop;
 mod names;
+mod palette;
+mod rewrite;
 mod types;
 
 #[proc_macro]
 pub fn define_error(attributes: TokenStream) -> TokenStream {
     let attrs = parse_macro_input!(attributes as DefineErrorArgs);
-    let DefineErrorArgs { type_def, variants } = attrs;
+    let DefineErrorArgs {
+        options,
+        type_def,
+        variants,
+    } = attrs;
 
-    let (variants, composed) = match split_variants(variants) {
-        Ok(v) => v,
-        Err(e) => return e.to_compile_error().into(),
-    };
+    if let Err(e) = reject_throws_only(&options) {
+        return e.into_compile_error().into();
+    }
 
-    let error = CompositeError {
-        name: type_def.name,
-        visibility: type_def.visibility,
+    match composite_error(type_def.name, type_def.visibility, variants, options) {
+        Ok(error) => error_definition(error).into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro]
+pub fn try_block(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TryBlockArgs);
+    let TryBlockArgs {
+        options,
+        name,
         variants,
-        composed,
-    };
+        block,
+    } = input;
+
+    if let Err(e) = reject_throws_only(&options) {
+        return e.into_compile_error().into();
+    }
+
+    let name = name.unwrap_or_else(|| Ident::new("TryBlockError", Span::mixed_site()));
+
+    match composite_error(name, Visibility::Inherited, variants, options) {
+        Ok(error) => try_block_closure(error, block).into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn reject_throws_only(options: &Options) -> Result<(), Error> {
+    let throws_only = [
+        options.report.as_ref().map(Ident::span),
+        options.infer.as_ref().map(Ident::span),
+        options.inferable.as_ref().map(Ident::span),
+        options.name.as_ref().map(LitStr::span),
+        options.module.as_ref().map(Ident::span),
+        options.result.as_ref().map(Spanned::span),
+        options.nested.as_ref().map(Ident::span),
+        options.auto_ok.as_ref().map(Ident::span),
+    ];
 
-    error_definition(error).into()
+    match throws_only.into_iter().flatten().next() {
+        Some(span) => Err(Error::new(
+            span,
+            "this option can only be used with #[throws]",
+        )),
+        None => Ok(()),
+    }
 }
 
 #[proc_macro_attribute]
@@ -45,48 +102,506 @@ pub fn throws(attributes: TokenStream, body: TokenStream) -> TokenStream {
             .into();
     };
 
+    let raw_attributes = attributes.clone();
     let attrs = parse_macro_input!(attributes as ThrowsArgs);
-    let ThrowsArgs { name, variants } = attrs;
 
-    let name = name.unwrap_or_else(|| fn_name_to_error(&function.sig.ident));
+    let result = if attrs.options.infer.is_some() {
+        infer_throws(raw_attributes.into(), &attrs, function)
+    } else {
+        expand_throws(attrs, function, &[])
+    };
+
+    match result {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
 
-    let (variants, composed) = match split_variants(variants) {
-        Ok(v) => v,
-        Err(e) => return e.to_compile_error().into(),
+#[doc(hidden)]
+#[proc_macro]
+pub fn __throws_infer(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as InferInput);
+
+    match continue_inference(input) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_attribute]
+pub fn throws_module(attributes: TokenStream, body: TokenStream) -> TokenStream {
+    let body = parse_macro_input!(body as Item);
+    let attrs = parse_macro_input!(attributes as ThrowsModuleArgs);
+
+    match expand_throws_module(attrs, body) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_throws(
+    args: ThrowsArgs,
+    function: ItemFn,
+    inferred: &[(Ident, Type)],
+) -> Result<TokenStream2, Error> {
+    if args.anon.is_some() {
+        let types = anon_types(&args.options, args.variants)?;
+        return Ok(patch_anon_function(function, &types).to_token_stream());
+    }
+
+    let error = function_error(args, &function.sig, &function.vis)?;
+    let companion = companion_macro(&function, &error);
+    let checks = variant_type_checks(&error.path(), inferred);
+    let (function, definition) = finish_function(function, error)?;
+
+    let mut stream = function.to_token_stream();
+    stream.extend(definition);
+    stream.extend(companion);
+    stream.extend(checks);
+
+    Ok(stream)
+}
+
+/// The error type generated for a function.
+struct FunctionError {
+    error: CompositeError,
+    module: Option<Ident>,
+}
+
+impl FunctionError {
+    /// Returns the path to the error type, relative to the function.
+    fn path(&self) -> Path {
+        let name = &self.error.name;
+
+        match &self.module {
+            Some(module) => parse_quote!(#module::#name),
+            None => parse_quote!(#name),
+        }
+    }
+
+    fn definition(self) -> TokenStream2 {
+        match self.module {
+            Some(module) => error_module(self.error, &module),
+            None => error_definition(self.error),
+        }
+    }
+}
+
+fn function_error(
+    args: ThrowsArgs,
+    sig: &Signature,
+    visibility: &Visibility,
+) -> Result<FunctionError, Error> {
+    let ThrowsArgs {
+        options,
+        anon: _,
+        name,
+        variants,
+    } = args;
+
+    let name = match (name, &options.name) {
+        (Some(name), None) => name,
+        (Some(name), Some(_)) => {
+            return Err(Error::new_spanned(
+                name,
+                "an explicit type name cannot be combined with the `name` option",
+            ))
+        }
+        (None, template) => {
+            let module = options.module.is_some();
+            fn_name_to_error(&sig.ident, template.as_ref(), module)?
+        }
     };
 
-    let error = CompositeError {
+    let module = options.module.is_some().then(|| sig.ident.clone());
+    let error = composite_error(name, visibility.clone(), variants, options)?;
+
+    Ok(FunctionError { error, module })
+}
+
+/// Changes the return type of the function and generates the definition of its error type.
+fn finish_function(
+    function: ItemFn,
+    error: FunctionError,
+) -> Result<(ItemFn, TokenStream2), Error> {
+    let options = &error.error.options;
+    let mut function = patch_function(function, error.path(), options)?;
+    convert_function_throws(&mut function, &error.error, &error.path());
+
+    if let Some(report) = &options.report {
+        if options.nested.is_some() {
+            return Err(Error::new_spanned(
+                report,
+                "the `report` option cannot be combined with `nested`",
+            ));
+        }
+
+        function = report_wrapper(function)?;
+    }
+
+    Ok((function, error.definition()))
+}
+
+fn composite_error(
+    name: Ident,
+    visibility: Visibility,
+    args: VariantArgs,
+    options: Options,
+) -> Result<CompositeError, Error> {
+    let naming = options
+        .naming
+        .as_ref()
+        .map_or_else(Naming::default, |(_, n)| *n);
+
+    let mut variants: Vec<Variant> = Vec::new();
+    let mut inline: Vec<InlineVariant> = Vec::new();
+    let mut composed: Vec<Type> = Vec::new();
+
+    for mut arg in args {
+        // The type is kept as written for metadata, but with `Self` replaced by the error name
+        let written: Option<Type> = match &arg {
+            VariantArg::Variant { typ, .. } if is_own_type(typ, &name) => {
+                Some(parse_quote_spanned!(typ.span()=> #name))
+            }
+            VariantArg::Variant { typ, .. } => Some(typ.clone()),
+            _ => None,
+        };
+
+        match &mut arg {
+            // The enum would be infinitely sized if it contained itself directly,
+            // unless the variant is shared and therefore already stored in an `Arc`
+            VariantArg::Variant {
+                typ,
+                name: variant,
+                context,
+                annotations,
+            } if is_own_type(typ, &name) => {
+                if context.is_none() {
+                    variant.get_or_insert_with(|| Ident::new(&name.to_string(), typ.span()));
+                }
+
+                let shared =
+                    options.shared.is_some() || annotations.as_ref().is_some_and(|a| a.shared);
+
+                *typ = if shared {
+                    parse_quote_spanned!(typ.span()=> #name)
+                } else {
+                    parse_quote_spanned!(typ.span()=> ::std::boxed::Box<#name>)
+                };
+            }
+            VariantArg::Composed { typ } if is_own_type(typ, &name) => {
+                return Err(Error::new_spanned(
+                    typ,
+                    "an error cannot be a suberror of itself, remove `break` to add a boxed variant instead",
+                ));
+            }
+            _ => {}
+        }
+
+        // Variants with context contain the error together with the context
+        if let VariantArg::Variant {
+            typ,
+            context: Some(context),
+            ..
+        } = &mut arg
+        {
+            *typ = parse_quote_spanned!(typ.span()=> ::throwing::WithContext<#typ, #context>);
+        }
+
+        if let VariantArg::Variant { typ, .. } | VariantArg::Composed { typ } = &arg {
+            let key = type_key(typ);
+            let previous = variants
+                .iter()
+                .map(|v| &v.typ)
+                .chain(&composed)
+                .find(|t| type_key(t) == key);
+
+            if let Some(previous) = previous {
+                let mut error = Error::new_spanned(typ, "this type is listed more than once");
+                error.combine(Error::new_spanned(previous, "first listed here"));
+                return Err(error);
+            }
+        }
+
+        match arg {
+            VariantArg::Variant {
+                typ,
+                context,
+                name,
+                annotations,
+            } => {
+                let explicit = name.is_some();
+
+                // Variants with context are named after the context
+                let name = name
+                    .or_else(|| type_to_variant(context.as_deref().unwrap_or(&typ), naming))
+                    .ok_or_else(|| {
+                        Error::new_spanned(
+                            &typ,
+                            "variant name can only be infered if the type is a path",
+                        )
+                    })?;
+
+                if explicit {
+                    check_variant_name(&name, &name, &variants, &inline)?;
+                } else {
+                    check_variant_name(&name, &typ, &variants, &inline)?;
+                }
+
+                let mut annotations = annotations.unwrap_or_default();
+                let mut typ = typ;
+
+                // Variants with context contain a `WithContext`, which can't be serialized
+                // or converted into a response
+                if let Some(context) = context
+                    .as_ref()
+                    .filter(|_| annotations.serde || annotations.response)
+                {
+                    return Err(Error::new_spanned(
+                        context,
+                        "variants with context cannot have the `serde` or `response` annotations",
+                    ));
+                }
+
+                // Shared variants are stored in an `Arc`, so that the error can be cloned
+                if options.shared.is_some() || annotations.shared {
+                    if annotations.serde || annotations.response {
+                        return Err(Error::new_spanned(
+                            &name,
+                            "shared variants cannot have the `serde` or `response` annotations",
+                        ));
+                    }
+
+                    annotations.shared = true;
+                    typ = parse_quote_spanned!(typ.span()=> ::std::sync::Arc<#typ>);
+                }
+
+                variants.push(Variant {
+                    typ,
+                    written: written.expect("variants should have a written type"),
+                    context,
+                    name,
+                    annotations,
+                })
+            }
+            VariantArg::Inline {
+                name,
+                fields,
+                message,
+                annotations,
+            } => {
+                let annotations = annotations.unwrap_or_default();
+
+                if annotations.serde || annotations.response {
+                    return Err(Error::new_spanned(
+                        &name,
+                        "inline variants cannot have the `serde` or `response` annotations",
+                    ));
+                }
+
+                check_variant_name(&name, &name, &variants, &inline)?;
+                check_constructor_name(&name, &options)?;
+
+                inline.push(InlineVariant {
+                    name,
+                    fields,
+                    message,
+                    annotations,
+                })
+            }
+            VariantArg::Composed { typ } => composed.push(typ),
+        }
+    }
+
+    // Variant names are attached to gRPC statuses as metadata values, which must be ASCII
+    if options.metadata.is_some() {
+        let mut names = variants
+            .iter()
+            .map(|v| &v.name)
+            .chain(inline.iter().map(|v| &v.name));
+
+        if let Some(name) = names.find(|name| !name.to_string().is_ascii()) {
+            return Err(Error::new_spanned(
+                name,
+                "variant names must be ASCII to be attached as metadata",
+            ));
+        }
+    }
+
+    Ok(CompositeError {
         name,
-        visibility: function.vis.clone(),
+        visibility,
         variants,
+        inline,
         composed,
+        options,
+    })
+}
+
+/// Checks whether the type refers to the error being defined, either by its name or by `Self`.
+fn is_own_type(typ: &Type, name: &Ident) -> bool {
+    let Type::Path(path) = typ else {
+        return false;
     };
 
-    let mut stream = patch_function(function, error.name.clone()).to_token_stream();
-    stream.extend(error_definition(error));
+    path.qself.is_none() && (path.path.is_ident(name) || path.path.is_ident("Self"))
+}
+
+fn check_variant_name(
+    name: &Ident,
+    tokens: &dyn ToTokens,
+    variants: &[Variant],
+    inline: &[InlineVariant],
+) -> Result<(), Error> {
+    let previous = variants
+        .iter()
+        .filter(|v| v.name == *name)
+        .map(|v| v.typ.to_token_stream())
+        .chain(
+            inline
+                .iter()
+                .filter(|v| v.name == *name)
+                .map(|v| v.name.to_token_stream()),
+        )
+        .next();
+
+    if let Some(previous) = previous {
+        let mut error = Error::new_spanned(
+            tokens,
+            format!(
+                "duplicate variant name `{name}`, use `as Name` to give one of the variants a different name"
+            ),
+        );
+
+        error.combine(Error::new_spanned(
+            previous,
+            format!("`{name}` is first used here"),
+        ));
+
+        return Err(error);
+    }
 
-    stream.into()
+    // Variants are also turned into method names, like `map_io_error` for `IoError`,
+    // so names that only differ in case or underscores would generate the same method
+    let method = variant_to_method(name);
+    let previous = variants
+        .iter()
+        .map(|v| &v.name)
+        .chain(inline.iter().map(|v| &v.name))
+        .find(|previous| variant_to_method(previous) == method);
+
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+
+    let mut error = Error::new_spanned(
+        tokens,
+        format!(
+            "variant name `{name}` generates the same method names as `{previous}`, use `as Name` to give one of the variants a different name"
+        ),
+    );
+
+    error.combine(Error::new_spanned(
+        previous,
+        format!("`{previous}` is first used here"),
+    ));
+
+    Err(error)
 }
 
-fn split_variants(args: VariantArgs) -> Result<(Vec<Variant>, Vec<Type>), Error> {
-    let mut variants = Vec::new();
-    let mut composed = Vec::new();
+/// Checks that the constructor of an inline variant doesn't clash with other generated methods.
+fn check_constructor_name(name: &Ident, options: &Options) -> Result<(), Error> {
+    let method = variant_to_method(name);
+
+    if method == "fold" || (options.io_error.is_some() && method == "try_from_io_error") {
+        return Err(Error::new_spanned(
+            name,
+            format!("the constructor of inline variant `{name}` would clash with the generated `{method}` method"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns a string that's equal for two types if they are written the same way.
+fn type_key(typ: &Type) -> String {
+    typ.to_token_stream().to_string()
+}
+
+const MAX_ANON_VARIANTS: usize = 8;
+
+fn anon_types(options: &Options, args: VariantArgs) -> Result<Vec<Type>, Error> {
+    let option = [
+        options.metadata.as_ref().map(LitStr::span),
+        options.report.as_ref().map(Ident::span),
+        options.io_error.as_ref().map(Ident::span),
+        options.naming.as_ref().map(|(key, _)| key.span()),
+        options.name.as_ref().map(LitStr::span),
+        options.module.as_ref().map(Ident::span),
+        options.infer.as_ref().map(Ident::span),
+        options.inferable.as_ref().map(Ident::span),
+        options.result.as_ref().map(Spanned::span),
+        options.nested.as_ref().map(Ident::span),
+        options.auto_ok.as_ref().map(Ident::span),
+        options.shared.as_ref().map(Ident::span),
+        options.partial_eq.as_ref().map(Ident::span),
+    ]
+    .into_iter()
+    .flatten()
+    .next();
+
+    if let Some(span) = option {
+        return Err(Error::new(
+            span,
+            "this option cannot be used with anonymous errors",
+        ));
+    }
+
+    let mut types = Vec::new();
 
     for arg in args {
-        match arg {
-            VariantArg::Variant { typ, name } => {
-                let name = name.or_else(|| type_to_variant(&typ)).ok_or_else(|| {
-                    Error::new_spanned(
-                        &typ,
-                        "variant name can only be infered if the type is a path",
-                    )
-                })?;
-
-                variants.push(Variant { typ, name })
+        let typ = match arg {
+            VariantArg::Variant {
+                typ,
+                context: None,
+                name: None,
+                annotations: None,
+            } => typ,
+            VariantArg::Variant { typ, .. } => {
+                return Err(Error::new_spanned(
+                    typ,
+                    "variants of anonymous errors cannot have names, context or annotations",
+                ))
             }
-            VariantArg::Composed { typ } => composed.push(typ),
+            VariantArg::Composed { typ } => {
+                return Err(Error::new_spanned(
+                    typ,
+                    "anonymous errors cannot have suberrors, list their variants instead",
+                ))
+            }
+            VariantArg::Inline { name, .. } => {
+                return Err(Error::new_spanned(
+                    name,
+                    "anonymous errors cannot have inline variants",
+                ))
+            }
+        };
+
+        if let Some(previous) = types.iter().find(|t| type_key(t) == type_key(&typ)) {
+            let mut error = Error::new_spanned(&typ, "this type is listed more than once");
+            error.combine(Error::new_spanned(previous, "first listed here"));
+            return Err(error);
+        }
+
+        if types.len() == MAX_ANON_VARIANTS {
+            return Err(Error::new_spanned(
+                typ,
+                format!("anonymous errors can have at most {MAX_ANON_VARIANTS} variants"),
+            ));
         }
+
+        types.push(typ);
     }
 
-    Ok((variants, composed))
+    Ok(types)
 }
@@ -1,37 +1,131 @@
-use proc_macro2::Span;
-use syn::{Ident, Path, Type};
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt, Error, GenericArgument, Ident, LitStr, PathArguments, Result, Type, TypePath,
+};
 
-pub fn type_to_variant(typ: &Type) -> Option<Ident> {
+use crate::types::Naming;
+
+const WRAPPERS: &[&str] = &["Box", "Arc", "Rc"];
+const PREFIXES: &[&str] = &["crate", "self", "super"];
+
+pub fn type_to_variant(typ: &Type, naming: Naming) -> Option<Ident> {
+    let mut name = String::new();
+    type_to_name(typ, naming, &mut name)?;
+
+    Some(Ident::new(&name, Span::mixed_site()))
+}
+
+fn type_to_name(typ: &Type, naming: Naming, name: &mut String) -> Option<()> {
     match typ {
-        Type::Path(path) => {
-            if path.qself.is_none() {
-                Some(path_to_variant(&path.path))
-            } else {
-                None
-            }
+        Type::Path(path) => path_to_name(path, naming, name),
+        Type::Reference(reference) if naming != Naming::Full => {
+            type_to_name(&reference.elem, naming, name)
         }
-        Type::Paren(paren) => type_to_variant(&paren.elem),
-        Type::Group(group) => type_to_variant(&group.elem),
+        Type::Paren(paren) => type_to_name(&paren.elem, naming, name),
+        Type::Group(group) => type_to_name(&group.elem, naming, name),
         _ => None,
     }
 }
 
-fn path_to_variant(path: &Path) -> Ident {
-    let mut name = String::new();
+fn path_to_name(path: &TypePath, naming: Naming, name: &mut String) -> Option<()> {
+    if path.qself.is_some() {
+        return None;
+    }
+
+    // The full path is used as it is, ignoring generic arguments
+    if naming == Naming::Full {
+        for segment in &path.path.segments {
+            snake_case_to_camel_case(&segment.ident, name);
+        }
+
+        return Some(());
+    }
+
+    let segments: Vec<_> = path
+        .path
+        .segments
+        .iter()
+        .skip_while(|segment| PREFIXES.iter().any(|prefix| segment.ident == prefix))
+        .collect();
 
-    for segment in &path.segments {
-        snake_case_to_camel_case(&segment.ident, &mut name);
+    let last = segments.last()?;
+
+    let args: Vec<&Type> = match &last.arguments {
+        PathArguments::None => Vec::new(),
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(typ) => Some(typ),
+                _ => None,
+            })
+            .collect(),
+        PathArguments::Parenthesized(_) => return None,
+    };
+
+    // Wrappers are named after the type they contain,
+    // unless it can't be named, like in `Box<dyn Error>`
+    if WRAPPERS.iter().any(|wrapper| last.ident == wrapper) {
+        if let [inner] = args[..] {
+            let mut inner_name = String::new();
+
+            if type_to_name(inner, naming, &mut inner_name).is_some() {
+                name.push_str(&inner_name);
+                return Some(());
+            }
+        }
+    } else {
+        // Generic arguments are prepended, so that `Error<Json>` becomes `JsonError`,
+        // unless some of them can't be named
+        let mut args_name = String::new();
+
+        if args
+            .iter()
+            .all(|arg| type_to_name(arg, naming, &mut args_name).is_some())
+        {
+            name.push_str(&args_name);
+        }
     }
 
-    Ident::new(&name, Span::mixed_site())
+    let count = match naming {
+        Naming::Full => unreachable!("full paths should be handled above"),
+        Naming::LastTwo => 2,
+        Naming::Last => 1,
+    };
+
+    for segment in &segments[segments.len().saturating_sub(count)..] {
+        snake_case_to_camel_case(&segment.ident, name);
+    }
+
+    Some(())
 }
 
-pub fn fn_name_to_error(ident: &Ident) -> Ident {
-    let mut name = String::new();
-    snake_case_to_camel_case(ident, &mut name);
-    name.push_str("Error");
+pub fn fn_name_to_error(ident: &Ident, template: Option<&LitStr>, module: bool) -> Result<Ident> {
+    let mut fn_name = String::new();
+    snake_case_to_camel_case(ident, &mut fn_name);
+
+    let Some(template) = template else {
+        let name = if module {
+            "Error".to_owned()
+        } else {
+            fn_name + "Error"
+        };
+
+        return Ok(Ident::new(&name, Span::mixed_site()));
+    };
+
+    let name = template.value().replace("{Fn}", &fn_name);
 
-    Ident::new(&name, Span::mixed_site())
+    match syn::parse_str::<Ident>(&name) {
+        Ok(ident) => Ok(Ident::new(&ident.to_string(), Span::mixed_site())),
+        Err(_) => Err(Error::new_spanned(
+            template,
+            format!(
+                "`{name}` is not a valid type name, the only supported placeholder is `{{Fn}}`"
+            ),
+        )),
+    }
 }
 
 pub fn snake_case_to_camel_case(ident: &Ident, name: &mut String) {
@@ -48,3 +142,84 @@ pub fn snake_case_to_camel_case(ident: &Ident, name: &mut String) {
         name.extend(chars);
     }
 }
+
+/// Converts the name of a variant to the name of a method, so `TooLong` becomes `too_long`.
+pub fn variant_to_method(ident: &Ident) -> Ident {
+    let chars: Vec<char> = ident.unraw().to_string().chars().collect();
+    let mut name = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next = chars.get(i + 1).copied();
+
+            if !prev.is_uppercase() || next.is_some_and(char::is_lowercase) {
+                name.push('_');
+            }
+        }
+
+        name.extend(c.to_lowercase());
+    }
+
+    // Keywords like `type` can't be used as method names
+    if syn::parse_str::<Ident>(&name).is_err() {
+        name.push('_');
+    }
+
+    Ident::new(&name, ident.span())
+}
+
+/// Formats a type for use in diagnostics, without most of the spaces `to_string()` adds.
+pub fn type_to_string(typ: &Type) -> String {
+    let mut string = String::new();
+    tokens_to_string(typ.to_token_stream(), &mut string);
+    string
+}
+
+const KEYWORDS: &[&str] = &["as", "dyn", "impl", "mut", "const"];
+
+fn tokens_to_string(tokens: TokenStream, string: &mut String) {
+    let mut after_word = false;
+    let mut after_keyword = false;
+
+    for token in tokens {
+        match token {
+            TokenTree::Ident(_) | TokenTree::Literal(_) => {
+                let word = token.to_string();
+
+                if after_word {
+                    string.push(' ');
+                }
+
+                string.push_str(&word);
+                after_word = true;
+                after_keyword = KEYWORDS.contains(&word.as_str());
+                continue;
+            }
+            // Keeps the space in paths like `dyn ::std::error::Error`
+            TokenTree::Punct(punct) if after_keyword => {
+                string.extend([' ', punct.as_char()]);
+            }
+            TokenTree::Punct(punct) => match punct.as_char() {
+                ',' | ';' => string.extend([punct.as_char(), ' ']),
+                '+' | '=' => string.extend([' ', punct.as_char(), ' ']),
+                c => string.push(c),
+            },
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => ("", ""),
+                };
+
+                string.push_str(open);
+                tokens_to_string(group.stream(), string);
+                string.push_str(close);
+            }
+        }
+
+        after_word = false;
+        after_keyword = false;
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeSet;
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    parse2, parse_quote, Attribute, Error, ImplItem, ImplItemFn, Item, ItemFn, ItemImpl, ItemMod,
+    Meta, Path, Result, Type,
+};
+
+use crate::{
+    anon_types,
+    attributes::{PaletteEntry, ThrowsArgs, ThrowsModuleArgs, VariantArg},
+    codegen::patch_anon_function,
+    finish_function, function_error,
+    infer::companion_macro,
+    type_key, FunctionError,
+};
+
+pub fn expand_throws_module(args: ThrowsModuleArgs, item: Item) -> Result<TokenStream> {
+    let palette: Vec<_> = args.palette.into_iter().collect();
+
+    for (i, entry) in palette.iter().enumerate() {
+        if let Some(previous) = palette[..i].iter().find(|e| e.alias == entry.alias) {
+            let mut error = Error::new_spanned(&entry.alias, "this alias is defined twice");
+            error.combine(Error::new_spanned(&previous.alias, "first defined here"));
+            return Err(error);
+        }
+    }
+
+    match item {
+        Item::Mod(module) => expand_mod(&palette, module),
+        Item::Impl(block) => expand_impl(&palette, block),
+        item => Err(Error::new_spanned(
+            item,
+            "the throws_module macro can only be used on modules and impl blocks",
+        )),
+    }
+}
+
+fn expand_mod(palette: &[PaletteEntry], mut module: ItemMod) -> Result<TokenStream> {
+    let Some((_, items)) = &mut module.content else {
+        return Err(Error::new_spanned(
+            module,
+            "the throws_module macro can only be used on modules with a body",
+        ));
+    };
+
+    let mut errors = Vec::new();
+
+    for (i, item) in items.iter_mut().enumerate() {
+        let Item::Fn(function) = item else {
+            continue;
+        };
+
+        let Some(args) = take_throws_args(&mut function.attrs, palette)? else {
+            continue;
+        };
+
+        if let Some(infer) = &args.options.infer {
+            return Err(Error::new_spanned(
+                infer,
+                "the `infer` option cannot be used inside throws_module",
+            ));
+        }
+
+        if args.anon.is_some() {
+            let types = anon_types(&args.options, args.variants)?;
+            *function = patch_anon_function(function.clone(), &types);
+        } else {
+            errors.push((i, function_error(args, &function.sig, &function.vis)?));
+        }
+    }
+
+    add_breaks(&mut errors);
+
+    for (i, error) in errors {
+        let Item::Fn(function) = &items[i] else {
+            unreachable!("only functions should have errors");
+        };
+
+        let companion = companion_macro(function, &error);
+        let (function, definition) = finish_function(function.clone(), error)?;
+        items[i] = Item::Verbatim(quote!(#function #definition #companion));
+    }
+
+    Ok(module.to_token_stream())
+}
+
+fn expand_impl(palette: &[PaletteEntry], mut block: ItemImpl) -> Result<TokenStream> {
+    let mut errors = Vec::new();
+
+    for (i, item) in block.items.iter_mut().enumerate() {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+
+        let Some(args) = take_throws_args(&mut method.attrs, palette)? else {
+            continue;
+        };
+
+        if let Some(infer) = &args.options.infer {
+            return Err(Error::new_spanned(
+                infer,
+                "the `infer` option cannot be used inside throws_module",
+            ));
+        }
+
+        if args.anon.is_some() {
+            let types = anon_types(&args.options, args.variants)?;
+            let function = patch_anon_function(method_to_function(method.clone()), &types);
+            *method = function_to_method(function, method);
+        } else {
+            errors.push((i, function_error(args, &method.sig, &method.vis)?));
+        }
+    }
+
+    add_breaks(&mut errors);
+
+    // Types can't be defined inside impl blocks, so they are placed after it
+    let mut definitions = TokenStream::new();
+
+    for (i, error) in errors {
+        let ImplItem::Fn(method) = &mut block.items[i] else {
+            unreachable!("only methods should have errors");
+        };
+
+        let (function, definition) = finish_function(method_to_function(method.clone()), error)?;
+        *method = function_to_method(function, method);
+        definitions.extend(definition);
+    }
+
+    Ok(quote!(#block #definitions))
+}
+
+/// Removes the `#[throws]` attribute and replaces aliases in its arguments.
+fn take_throws_args(
+    attrs: &mut Vec<Attribute>,
+    palette: &[PaletteEntry],
+) -> Result<Option<ThrowsArgs>> {
+    let Some(index) = attrs.iter().position(|a| is_throws(a.path())) else {
+        return Ok(None);
+    };
+
+    if let Some(repeated) = attrs[index + 1..].iter().find(|a| is_throws(a.path())) {
+        return Err(Error::new_spanned(
+            repeated,
+            "only one `#[throws]` attribute can be used on a function",
+        ));
+    }
+
+    let mut args: ThrowsArgs = match attrs.remove(index).meta {
+        Meta::Path(_) => parse2(TokenStream::new())?,
+        Meta::List(list) => list.parse_args()?,
+        Meta::NameValue(meta) => {
+            return Err(Error::new_spanned(
+                meta,
+                "expected arguments in parentheses",
+            ))
+        }
+    };
+
+    let anon = args.anon.is_some();
+
+    for arg in &mut args.variants {
+        let VariantArg::Variant {
+            typ,
+            context,
+            name,
+            annotations,
+        } = arg
+        else {
+            continue;
+        };
+
+        let Type::Path(path) = &*typ else {
+            continue;
+        };
+
+        let Some(entry) = palette
+            .iter()
+            .find(|e| path.qself.is_none() && path.path.is_ident(&e.alias))
+        else {
+            continue;
+        };
+
+        *typ = entry.typ.clone();
+
+        // Anonymous errors don't have variant names and variants with context
+        // are named after the context
+        if !anon {
+            if context.is_none() {
+                name.get_or_insert_with(|| entry.alias.clone());
+            }
+
+            if annotations.is_none() {
+                annotations.clone_from(&entry.annotations);
+            }
+        }
+    }
+
+    Ok(Some(args))
+}
+
+fn is_throws(path: &Path) -> bool {
+    let segments: Vec<_> = path.segments.iter().map(|s| &s.ident).collect();
+
+    match segments[..] {
+        [name] => path.leading_colon.is_none() && name == "throws",
+        [krate, name] => krate == "throwing" && name == "throws",
+        _ => false,
+    }
+}
+
+/// Adds every error whose variants are a subset of another error as its suberror.
+///
+/// Errors with the same variants are only added to the errors declared after them,
+/// so that they don't break into each other.
+fn add_breaks(errors: &mut [(usize, FunctionError)]) {
+    let keys: Vec<BTreeSet<String>> = errors
+        .iter()
+        .map(|(_, e)| e.error.variants.iter().map(|v| type_key(&v.typ)).collect())
+        .collect();
+
+    // Errors with inline variants can't be suberrors
+    let breakable: Vec<bool> = errors
+        .iter()
+        .map(|(_, e)| e.error.inline.is_empty())
+        .collect();
+
+    let types: Vec<Type> = errors
+        .iter()
+        .map(|(_, e)| {
+            let path = e.path();
+            parse_quote!(#path)
+        })
+        .collect();
+
+    for (i, (_, error)) in errors.iter_mut().enumerate() {
+        for (j, typ) in types.iter().enumerate() {
+            if i == j || !keys[j].is_subset(&keys[i]) || !breakable[j] {
+                continue;
+            }
+
+            if j > i && keys[j] == keys[i] {
+                continue;
+            }
+
+            let key = type_key(typ);
+            let listed = error
+                .error
+                .variants
+                .iter()
+                .map(|v| &v.typ)
+                .chain(&error.error.composed)
+                .any(|t| type_key(t) == key);
+
+            if !listed {
+                error.error.composed.push(typ.clone());
+            }
+        }
+    }
+}
+
+fn method_to_function(method: ImplItemFn) -> ItemFn {
+    ItemFn {
+        attrs: method.attrs,
+        vis: method.vis,
+        sig: method.sig,
+        block: Box::new(method.block),
+    }
+}
+
+fn function_to_method(function: ItemFn, original: &ImplItemFn) -> ImplItemFn {
+    ImplItemFn {
+        attrs: function.attrs,
+        vis: function.vis,
+        defaultness: original.defaultness,
+        sig: function.sig,
+        block: *function.block,
+    }
+}
@@ -0,0 +1,314 @@
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
+use syn::{
+    parse_quote, parse_quote_spanned,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    visit_mut::{self, VisitMut},
+    Block, Expr, Item, Macro, Path, PathSegment, Stmt, StmtMacro, Token, Type, TypePath,
+};
+
+/// What the `?` operator is rewritten to.
+#[derive(Clone, Copy)]
+enum TryTarget {
+    /// Converts errors into a `OneOf` union.
+    ///
+    /// Since `From` can't be implemented for each member of a generic union,
+    /// `?` is expanded manually into a match that uses `IntoOneOf` instead.
+    Union,
+    /// Returns `None` for options and `Some(Err(...))` for results,
+    /// since `?` can't be used on results in functions returning `Option<Result<T, E>>`.
+    Nested,
+}
+
+/// Rewrites the `?` operator in the body of a function.
+///
+/// Closures, async blocks and nested items are left alone,
+/// since `?` inside them doesn't return from the function.
+struct RewriteTry {
+    target: TryTarget,
+    rewritten: bool,
+}
+
+impl VisitMut for RewriteTry {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::Try(try_expr) => {
+                self.visit_expr_mut(&mut try_expr.expr);
+
+                let inner = &try_expr.expr;
+                let span = try_expr.question_token.span;
+                let value = Ident::new("value", Span::mixed_site());
+                let error = Ident::new("error", Span::mixed_site());
+
+                let rewritten = match self.target {
+                    TryTarget::Union => parse_quote_spanned!(span=>
+                        match #inner {
+                            ::core::result::Result::Ok(#value) => #value,
+                            ::core::result::Result::Err(#error) => {
+                                return ::core::result::Result::Err(
+                                    ::throwing::__private::IntoOneOf::into_one_of(#error),
+                                );
+                            }
+                        }
+                    ),
+                    TryTarget::Nested => parse_quote_spanned!(span=>
+                        match ::throwing::__private::NestedTry::branch(#inner) {
+                            ::core::ops::ControlFlow::Continue(#value) => #value,
+                            ::core::ops::ControlFlow::Break(#error) => return #error,
+                        }
+                    ),
+                };
+
+                *expr = rewritten;
+                self.rewritten = true;
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut Item) {}
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        if !contains_question_mark(mac.tokens.clone()) {
+            return;
+        }
+
+        // Macro arguments are only rewritten if they look like a list of expressions,
+        // which covers macros like `println!()` or `vec![]`.
+        let Ok(mut args) = mac.parse_body_with(Punctuated::<Expr, Token!(,)>::parse_terminated)
+        else {
+            return;
+        };
+
+        let mut visitor = RewriteTry {
+            target: self.target,
+            rewritten: false,
+        };
+
+        for arg in &mut args {
+            visitor.visit_expr_mut(arg);
+        }
+
+        if visitor.rewritten {
+            let span = mac.delimiter.span().join();
+            mac.tokens = quote_spanned!(span=> #args);
+            self.rewritten = true;
+        }
+    }
+}
+
+/// Checks for a `?` token, so that macros without one don't have to be parsed.
+fn contains_question_mark(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Punct(punct) => punct.as_char() == '?',
+        TokenTree::Group(group) => contains_question_mark(group.stream()),
+        _ => false,
+    })
+}
+
+/// Only used for `anon` functions, other functions rely on [`From`] implementations.
+pub fn convert_try_to_union(block: &mut Block) {
+    RewriteTry {
+        target: TryTarget::Union,
+        rewritten: false,
+    }
+    .visit_block_mut(block);
+}
+
+/// Used for functions with the `nested` option returning `Option<Result<T, E>>`.
+pub fn convert_try_in_option(block: &mut Block) {
+    RewriteTry {
+        target: TryTarget::Nested,
+        rewritten: false,
+    }
+    .visit_block_mut(block);
+}
+
+/// Wraps values returned from the function with `return` in `Ok`.
+///
+/// Like with the rewriting of `?`, closures, async blocks and nested items are left alone.
+struct WrapReturns;
+
+impl VisitMut for WrapReturns {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::Return(ret) => {
+                let value = match ret.expr.take() {
+                    Some(mut value) => {
+                        self.visit_expr_mut(&mut value);
+                        quote!(#value)
+                    }
+                    None => quote!(()),
+                };
+
+                let span = ret.span();
+                ret.expr = Some(parse_quote_spanned!(span=> ::core::result::Result::Ok(#value)));
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut Item) {}
+}
+
+/// Wraps the tail expression of the block and all returned values in `Ok`.
+pub fn wrap_in_ok(block: &mut Block) {
+    WrapReturns.visit_block_mut(block);
+
+    match block.stmts.last_mut() {
+        Some(Stmt::Expr(Expr::Return(_), _)) => {}
+        // Macros like `throw!()` or `todo!()` may never return, so the lint for unreachable
+        // code has to be allowed, which only works on statements
+        Some(
+            stmt @ (Stmt::Expr(Expr::Macro(_), None)
+            | Stmt::Macro(StmtMacro {
+                semi_token: None, ..
+            })),
+        ) => {
+            let span = stmt.span();
+            *stmt = parse_quote_spanned!(span=>
+                #[allow(unreachable_code, clippy::diverging_sub_expression)]
+                return ::core::result::Result::Ok(#stmt);
+            );
+        }
+        Some(Stmt::Expr(expr, None)) => {
+            let span = expr.span();
+            *expr = parse_quote_spanned!(span=> ::core::result::Result::Ok(#expr));
+        }
+        _ => block.stmts.push(parse_quote!(
+            #[allow(unreachable_code)]
+            return ::core::result::Result::Ok(());
+        )),
+    }
+}
+
+/// Rewrites uses of the `throw!()` macro to convert the error with the given function.
+///
+/// This allows type-specific diagnostics when the error can't be converted.
+/// Like with `?`, closures, async blocks and nested items are left alone,
+/// so `throw!()` inside them falls back to using `From`.
+struct RewriteThrow<'a> {
+    convert: &'a TokenStream,
+    rewritten: bool,
+}
+
+impl RewriteThrow<'_> {
+    /// The macro is kept and receives the converted error,
+    /// so that imports of it are still used and it converts the error with `From` as usual.
+    fn rewrite(&mut self, mac: &mut Macro) {
+        if !is_throw(&mac.path) {
+            return;
+        }
+
+        let Ok(mut args) = mac.parse_body_with(Punctuated::<Expr, Token!(,)>::parse_terminated)
+        else {
+            return;
+        };
+
+        let (Some(error), true) = (args.pop(), args.is_empty()) else {
+            return;
+        };
+
+        let mut error = error.into_value();
+        self.visit_expr_mut(&mut error);
+
+        let convert = self.convert;
+        mac.tokens = quote!(#convert(#error));
+        self.rewritten = true;
+    }
+}
+
+impl VisitMut for RewriteThrow<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::Macro(mac) => self.rewrite(&mut mac.mac),
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Macro(mac) => self.rewrite(&mut mac.mac),
+            _ => visit_mut::visit_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut Item) {}
+}
+
+/// Checks whether the path refers to the `throw!()` macro of this crate.
+fn is_throw(path: &Path) -> bool {
+    let segments: Vec<_> = path.segments.iter().map(|s| &s.ident).collect();
+
+    match segments[..] {
+        [name] => path.leading_colon.is_none() && name == "throw",
+        [krate, name] => krate == "throwing" && name == "throw",
+        _ => false,
+    }
+}
+
+/// Rewrites all uses of `throw!()` to use the given conversion function.
+///
+/// Returns whether any were found.
+pub fn convert_throw(block: &mut Block, convert: &TokenStream) -> bool {
+    let mut visitor = RewriteThrow {
+        convert,
+        rewritten: false,
+    };
+
+    visitor.visit_block_mut(block);
+    visitor.rewritten
+}
+
+/// Adds another `super` segment to paths starting with `super`.
+///
+/// This keeps relative paths working after a type is moved into a child module.
+struct NestSuper;
+
+impl VisitMut for NestSuper {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        visit_mut::visit_path_mut(self, path);
+
+        let starts_with_super = path.leading_colon.is_none()
+            && path
+                .segments
+                .first()
+                .is_some_and(|segment| segment.ident == "super");
+
+        if starts_with_super {
+            let ident = Ident::new("super", path.segments[0].ident.span());
+            path.segments.insert(0, PathSegment::from(ident));
+        }
+    }
+}
+
+pub fn nest_super_paths(typ: &mut Type) {
+    NestSuper.visit_type_mut(typ);
+}
+
+/// Replaces paths consisting of just the name of an error with the full path to it.
+///
+/// Recursive variants refer to the error by its name,
+/// which isn't in scope outside of the module it's defined in.
+struct QualifyName<'a> {
+    name: &'a Ident,
+    path: &'a Path,
+}
+
+impl VisitMut for QualifyName<'_> {
+    fn visit_type_path_mut(&mut self, typ: &mut TypePath) {
+        if typ.qself.is_none() && typ.path.is_ident(self.name) {
+            typ.path = self.path.clone();
+        } else {
+            visit_mut::visit_type_path_mut(self, typ);
+        }
+    }
+}
+
+pub fn qualify_error_name(typ: &mut Type, name: &Ident, path: &Path) {
+    QualifyName { name, path }.visit_type_mut(typ);
+}
@@ -1,13 +1,64 @@
-use syn::{Ident, Type, Visibility};
+use syn::{FieldsNamed, Ident, LitStr, Path, Type, Visibility};
 
 pub struct CompositeError {
     pub visibility: Visibility,
     pub name: Ident,
     pub variants: Vec<Variant>,
+    pub inline: Vec<InlineVariant>,
     pub composed: Vec<Type>,
+    pub options: Options,
 }
 
 pub struct Variant {
     pub typ: Type,
+    /// The type as it was written, before being wrapped in a `WithContext`, `Arc` or `Box`.
+    pub written: Type,
+    pub context: Option<Box<Type>>,
     pub name: Ident,
+    pub annotations: Annotations,
+}
+
+/// A variant that doesn't wrap another error, but has its own message.
+pub struct InlineVariant {
+    pub name: Ident,
+    pub fields: Option<FieldsNamed>,
+    pub message: LitStr,
+    pub annotations: Annotations,
+}
+
+#[derive(Clone, Default)]
+pub struct Annotations {
+    pub code: Option<Ident>,
+    pub grpc: Option<Ident>,
+    pub serde: bool,
+    pub status: Option<u16>,
+    pub response: bool,
+    pub shared: bool,
+    pub exit: Option<u8>,
+    pub io_kind: Option<Ident>,
+}
+
+#[derive(Default)]
+pub struct Options {
+    pub metadata: Option<LitStr>,
+    pub report: Option<Ident>,
+    pub io_error: Option<Ident>,
+    pub naming: Option<(Ident, Naming)>,
+    pub name: Option<LitStr>,
+    pub module: Option<Ident>,
+    pub infer: Option<Ident>,
+    pub inferable: Option<Ident>,
+    pub result: Option<Path>,
+    pub nested: Option<Ident>,
+    pub auto_ok: Option<Ident>,
+    pub shared: Option<Ident>,
+    pub partial_eq: Option<Ident>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Naming {
+    #[default]
+    Full,
+    LastTwo,
+    Last,
 }
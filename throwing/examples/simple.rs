@@ -1,5 +1,4 @@
 /// Adds newline seperated numbers from stdin
-
 use std::{
     io::{self, stdin, stdout, BufRead, Write},
     num::ParseIntError,
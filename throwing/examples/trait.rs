@@ -1,9 +1,4 @@
-use std::{
-    error::Error,
-    fmt::{self, Display, Formatter},
-    num::ParseIntError,
-    str::FromStr,
-};
+use std::{num::ParseIntError, str::FromStr};
 
 use throwing_macros::{define_error, throws};
 
@@ -14,14 +9,16 @@ pub struct Fraction {
     denominator: u64,
 }
 
-define_error!(pub type ParseFractionError = ParseIntError | BadLengthError);
+define_error!(pub type ParseFractionError = ParseIntError | BadLength = "bad length");
 
 impl FromStr for Fraction {
     type Err = ParseFractionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let components: Vec<&str> = s.split('/').collect();
-        let components: [&str; 2] = components.try_into().map_err(|_| BadLengthError)?;
+        let components: [&str; 2] = components
+            .try_into()
+            .map_err(|_| ParseFractionError::BadLength)?;
 
         let numerator = components[0].parse()?;
         let denominator = components[1].parse()?;
@@ -40,16 +37,3 @@ fn main() {
 
     Ok(())
 }
-
-// A simple error like this can be conveniently created with thiserror
-
-#[derive(Debug, Clone, Copy)]
-pub struct BadLengthError;
-
-impl Display for BadLengthError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "bad length")
-    }
-}
-
-impl Error for BadLengthError {}
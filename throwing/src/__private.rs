@@ -0,0 +1,300 @@
+//! Items used by the code generated by the macros.
+//!
+//! Nothing in this module is considered part of the public API.
+
+use std::marker::PhantomData;
+
+use crate::{
+    one_of::{Contains, OneOf, Subset, SubsetOf, Union},
+    SubError, ThrowingError, Widened,
+};
+
+#[cfg(feature = "serde")]
+pub use serde;
+
+#[cfg(feature = "serde")]
+pub struct Message<'a, T: ?Sized>(pub &'a T);
+
+#[cfg(feature = "serde")]
+impl<T: core::fmt::Display + ?Sized> serde::Serialize for Message<'_, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self.0)
+    }
+}
+
+#[cfg(feature = "axum")]
+pub use axum_core;
+
+#[cfg(feature = "axum")]
+pub fn problem_details(
+    status: u16,
+    kind: &str,
+    code: Option<&str>,
+    detail: &dyn core::fmt::Display,
+) -> axum_core::response::Response {
+    use axum_core::response::IntoResponse;
+    use http::{header::CONTENT_TYPE, StatusCode};
+
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    let mut body = serde_json::json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or("Unknown Error"),
+        "status": status.as_u16(),
+        "detail": detail.to_string(),
+        "kind": kind,
+    });
+
+    if let Some(code) = code {
+        body["code"] = code.into();
+    }
+
+    let headers = [(CONTENT_TYPE, "application/problem+json")];
+    (status, headers, body.to_string()).into_response()
+}
+
+/// Wraps a variant without a `status` annotation to pick how it's converted into a response.
+///
+/// Types implementing `IntoResponse` are converted with it, since the method of
+/// `DelegateResponse` is found before the one of `DefaultResponse`, which needs an autoref.
+#[cfg(feature = "axum")]
+pub struct Respond<T>(pub T);
+
+#[cfg(feature = "axum")]
+pub trait DelegateResponse {
+    fn respond(self, kind: &str, code: Option<&str>) -> axum_core::response::Response;
+}
+
+#[cfg(feature = "axum")]
+impl<T: axum_core::response::IntoResponse> DelegateResponse for Respond<T> {
+    fn respond(self, _kind: &str, _code: Option<&str>) -> axum_core::response::Response {
+        self.0.into_response()
+    }
+}
+
+#[cfg(feature = "axum")]
+pub trait DefaultResponse {
+    fn respond(self, kind: &str, code: Option<&str>) -> axum_core::response::Response;
+}
+
+#[cfg(feature = "axum")]
+impl<T: core::fmt::Display> DefaultResponse for &Respond<T> {
+    fn respond(self, kind: &str, code: Option<&str>) -> axum_core::response::Response {
+        problem_details(500, kind, code, &self.0)
+    }
+}
+
+#[cfg(feature = "tonic")]
+pub use tonic;
+
+#[cfg(feature = "tonic")]
+pub fn grpc_status(
+    code: tonic::Code,
+    kind: &'static str,
+    metadata: Option<&'static str>,
+    message: &dyn core::fmt::Display,
+) -> tonic::Status {
+    let mut status = tonic::Status::new(code, message.to_string());
+
+    if let Some(key) = metadata {
+        let value = tonic::metadata::MetadataValue::from_static(kind);
+        status.metadata_mut().insert(key, value);
+    }
+
+    status
+}
+
+pub fn cast<T: 'static, U: 'static>(value: T) -> Result<U, T> {
+    let mut slot = Some(value);
+
+    match (&mut slot as &mut dyn core::any::Any).downcast_mut::<Option<U>>() {
+        Some(value) => Ok(value.take().expect("slot should be full")),
+        None => Err(slot.expect("slot should be full")),
+    }
+}
+
+pub fn downcast_io_error<E>(error: std::io::Error) -> Result<E, std::io::Error>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    if !error.get_ref().is_some_and(|e| e.is::<E>()) {
+        return Err(error);
+    }
+
+    let inner = error.into_inner().expect("error should have an inner error");
+    Ok(*inner.downcast().expect("inner error should have the correct type"))
+}
+
+pub struct Member<I>(I);
+
+pub trait IntoOneOf<T, I> {
+    fn into_one_of(self) -> T;
+}
+
+impl<T, U: Contains<T, I>, I> IntoOneOf<OneOf<U>, Member<I>> for T {
+    fn into_one_of(self) -> OneOf<U> {
+        OneOf::new(self)
+    }
+}
+
+impl<T: SubsetOf<U, I>, U: Union, I> IntoOneOf<OneOf<U>, Subset<I>> for OneOf<T> {
+    fn into_one_of(self) -> OneOf<U> {
+        self.widen()
+    }
+}
+
+pub struct Widening<I>(I);
+
+impl<E: Upcast<OneOf<U>, I>, U: Union, I> IntoOneOf<OneOf<U>, Widening<I>> for Widened<E> {
+    fn into_one_of(self) -> OneOf<U> {
+        self.0.upcast()
+    }
+}
+
+/// Converts an error into a super error, including conversions into unions.
+///
+/// Conversions into unions need an extra parameter to tell apart the positions of the types,
+/// which is why they can't be implemented with [`SubError`].
+pub trait Upcast<T, I> {
+    fn upcast(self) -> T;
+}
+
+impl<E: SubError<T>, T> Upcast<T, ()> for E {
+    fn upcast(self) -> T {
+        self.to_super_error()
+    }
+}
+
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a suberror",
+    note = "only errors generated by `throwing` without inline variants and `OneOf` unions can be used with `break`"
+)]
+pub trait Variants {
+    type List;
+}
+
+#[diagnostic::on_unimplemented(
+    message = "`{Sub}` cannot be broken into `{Self}`: missing variant `{V}`",
+    label = "`{Self}` has no variant of type `{V}`",
+    note = "add `{V}` to the variants of `{Self}`"
+)]
+pub trait HasVariant<V, Sub> {}
+
+pub trait BreaksInto<Super, List> {}
+
+impl<Sub, Super> BreaksInto<Super, ()> for Sub {}
+
+impl<Sub, Super, V, Rest> BreaksInto<Super, (V, Rest)> for Sub
+where
+    Super: HasVariant<V, Sub>,
+    Sub: BreaksInto<Super, Rest>,
+{
+}
+
+pub struct Break<Sub, Super>(pub Sub, pub PhantomData<Super>);
+
+pub trait BreakInto<Super> {
+    fn break_into(self) -> Super;
+}
+
+impl<Sub: SubError<Super>, Super> BreakInto<Super> for Break<Sub, Super> {
+    fn break_into(self) -> Super {
+        self.0.to_super_error()
+    }
+}
+
+pub trait CheckBreak<Sub, Super> {
+    fn break_into(self) -> Super
+    where
+        Sub: Variants + BreaksInto<Super, <Sub as Variants>::List>;
+}
+
+impl<Sub, Super> CheckBreak<Sub, Super> for &Break<Sub, Super> {
+    fn break_into(self) -> Super
+    where
+        Sub: Variants + BreaksInto<Super, <Sub as Variants>::List>,
+    {
+        unreachable!("suberrors that pass the check should implement `SubError`")
+    }
+}
+
+pub struct Nested<'a, T>(pub &'a T);
+
+pub trait HasNested<'a> {
+    fn nested(&self) -> Option<&'a dyn ThrowingError>;
+}
+
+impl<'a, T: ThrowingError> HasNested<'a> for Nested<'a, T> {
+    fn nested(&self) -> Option<&'a dyn ThrowingError> {
+        Some(self.0)
+    }
+}
+
+pub trait NoNested<'a> {
+    fn nested(&self) -> Option<&'a dyn ThrowingError> {
+        None
+    }
+}
+
+impl<'a, T> NoNested<'a> for &Nested<'a, T> {}
+
+pub fn type_name_of<T>(_: &T) -> &'static str {
+    core::any::type_name::<T>()
+}
+
+pub use throwing_macros::__throws_infer as throws_infer;
+
+pub trait NestedTry<R> {
+    type Value;
+
+    fn branch(self) -> core::ops::ControlFlow<R, Self::Value>;
+}
+
+impl<T, R> NestedTry<Option<R>> for Option<T> {
+    type Value = T;
+
+    fn branch(self) -> core::ops::ControlFlow<Option<R>, T> {
+        match self {
+            Some(value) => core::ops::ControlFlow::Continue(value),
+            None => core::ops::ControlFlow::Break(None),
+        }
+    }
+}
+
+impl<T, E, U, F: From<E>> NestedTry<Option<Result<U, F>>> for Result<T, E> {
+    type Value = T;
+
+    fn branch(self) -> core::ops::ControlFlow<Option<Result<U, F>>, T> {
+        match self {
+            Ok(value) => core::ops::ControlFlow::Continue(value),
+            Err(error) => core::ops::ControlFlow::Break(Some(Err(F::from(error)))),
+        }
+    }
+}
+
+pub trait VariantType<const N: usize> {
+    type Type;
+}
+
+#[diagnostic::on_unimplemented(
+    message = "inferred variants with the same name have different types `{Self}` and `{T}`",
+    note = "list one of the variants explicitly with a different name using `as`"
+)]
+pub trait SameVariantType<T> {}
+
+impl<T> SameVariantType<T> for T {}
+
+pub fn check_variant_type<T, U: SameVariantType<T>, E>(_: fn(U) -> E) {}
+
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be cloned, but the error has shared variants",
+    label = "`{Self}` doesn't implement `Clone`",
+    note = "variants with the `shared` annotation are stored in an `Arc`, so that they can be cloned"
+)]
+pub trait CloneVariant: Clone {}
+
+impl<T: Clone> CloneVariant for T {}
+
+pub fn clone_variant<T: CloneVariant>(variant: &T) -> T {
+    variant.clone()
+}
@@ -0,0 +1,119 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+/// An error together with a value describing what was being done when it occurred.
+///
+/// Variants declared as `Type with Context` in [`#[throws]`][crate::throws]
+/// and [`define_error!()`][crate::define_error] contain a value of this type.
+/// It's displayed as just the context and its [`Error::source()`] is the wrapped error,
+/// so that printing the chain of sources doesn't repeat the error.
+/// The context and the error are therefore not combined into a single message,
+/// but [`report()`][crate::report] prints them one after the other.
+/// To combine them, format both fields, as in `format!("{}: {}", e.context, e.error)`.
+///
+/// Values of this type are created by [`Context::context()`] and [`Context::with_context()`].
+pub struct WithContext<E, C> {
+    /// The error that occurred.
+    pub error: E,
+    /// The context the error occurred in.
+    pub context: C,
+}
+
+impl<E, C> WithContext<E, C> {
+    /// Returns the wrapped error, discarding the context.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E, C: Display> Display for WithContext<E, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.context, f)
+    }
+}
+
+impl<E: Debug, C: Debug> Debug for WithContext<E, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithContext")
+            .field("error", &self.error)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl<E, C> Error for WithContext<E, C>
+where
+    E: Error + 'static,
+    C: Display + Debug,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Extension trait for attaching context to the error of a [`Result`].
+///
+/// Unlike with string-based context, the context is a value of any type,
+/// which becomes part of the variant of the generated error.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{throws, Context};
+/// # use std::{error::Error, fmt, fs, io};
+/// #[derive(Debug)]
+/// struct ReadConfig {
+///     path: String,
+/// }
+///
+/// impl fmt::Display for ReadConfig {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         write!(f, "failed to read config from {}", self.path)
+///     }
+/// }
+///
+/// #[throws(io::Error with ReadConfig)]
+/// fn load_config(path: String) -> String {
+///     Ok(fs::read_to_string(&path).context(ReadConfig { path })?)
+/// }
+///
+/// # fn main() {
+/// match load_config("config.toml".to_owned()) {
+///     Ok(_) => {}
+///     Err(LoadConfigError::ReadConfig(e)) => println!("{} is missing", e.context.path),
+/// }
+///
+/// let error = load_config("missing.toml".to_owned()).unwrap_err();
+/// assert_eq!(error.to_string(), "failed to read config from missing.toml");
+/// assert!(error.source().unwrap().is::<io::Error>());
+/// # }
+/// ```
+pub trait Context<T, E> {
+    /// Attaches the given context to the error.
+    fn context<C>(self, context: C) -> Result<T, WithContext<E, C>>;
+
+    /// Attaches the context returned by the closure to the error.
+    ///
+    /// The closure is only called if the result is an error.
+    fn with_context<C, F>(self, context: F) -> Result<T, WithContext<E, C>>
+    where
+        F: FnOnce() -> C;
+}
+
+impl<T, E> Context<T, E> for Result<T, E> {
+    fn context<C>(self, context: C) -> Result<T, WithContext<E, C>> {
+        self.map_err(|error| WithContext { error, context })
+    }
+
+    fn with_context<C, F>(self, context: F) -> Result<T, WithContext<E, C>>
+    where
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| WithContext {
+            error,
+            context: context(),
+        })
+    }
+}
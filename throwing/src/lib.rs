@@ -12,10 +12,50 @@
 //! Additionally, it can generate [`From<T>`] implementation for upcasting errors,
 //! that is converting an error of a type with fewer variants to one with more variants.
 //! 
+//! For cases where naming an error type isn't worth it, the [`OneOf`] type
+//! can be used as an anonymous union of errors.
+//!
+//! # Features
+//!
+//! - `serde`: Implements [`Serialize`][serde-ser] for all generated errors,
+//!   and [`Deserialize`][serde-de] for those with variants marked `[serde]`.
+//!   See [`#[throws]`][throws#serde] for details.
+//! - `axum`: Implements [`IntoResponse`][axum-response] for all generated errors.
+//!   See [`#[throws]`][throws#axum] for details.
+//! - `tonic`: Implements conversions from all generated errors to `tonic::Status`.
+//!   See [`#[throws]`][throws#tonic] for details.
+//!
+//! # Minimum supported Rust version
+//!
+//! The minimum supported Rust version is 1.78,
+//! which is needed for the readable diagnostics reported when suberrors are missing variants.
+//!
 //! [java-throws]: https://docs.oracle.com/javase/tutorial/essential/exceptions/declaring.html
+//! [serde-ser]: https://docs.rs/serde/latest/serde/trait.Serialize.html
+//! [serde-de]: https://docs.rs/serde/latest/serde/trait.Deserialize.html
+//! [axum-response]: https://docs.rs/axum/latest/axum/response/trait.IntoResponse.html
+
+#[doc(hidden)]
+pub mod __private;
+mod context;
+mod metadata;
+pub mod one_of;
+pub mod testing;
+mod widen;
+
+pub use context::{Context, WithContext};
+pub use metadata::{ErrorMetadata, VariantInfo};
+pub use one_of::OneOf;
+pub use widen::{Widen, WidenResult, Widened};
+
+use std::{
+    error::Error,
+    io::{self, Write},
+    process::{ExitCode, Termination},
+};
 
 #[cfg(doc)]
-use std::{error::Error, fmt::Display};
+use std::fmt::{Debug, Display};
 
 /// Creates a new composite error type with a given name.
 ///
@@ -26,6 +66,8 @@ use std::{error::Error, fmt::Display};
 /// it must be specified explicitly with `type SomeError = ...`.
 /// Unlike [`#[throws]`][throws], this macro also accepts a visibility declaration,
 /// since it cannot be copied from the function.
+/// Options, if any, are placed before the type declaration,
+/// for example `define_error!(metadata = "kind", pub type SomeError = ...)`.
 ///
 /// # Examples
 ///
@@ -124,6 +166,7 @@ pub use throwing_macros::define_error;
 /// (Hence "breaking" its variants apart.)
 /// To do this, its variants have to be a subset of the errors the function
 /// can return.
+/// If they aren't, compilation fails with an error naming the missing variant.
 ///
 /// Suppose you have two functions:
 ///
@@ -150,9 +193,42 @@ pub use throwing_macros::define_error;
 /// Similarly, `FirstError::BarError` will map to `SecondError::BarError`.
 /// `SecondError::BazError` can never be returned.
 ///
+/// Leaving out `BarError` from the variants of `second()` instead fails with
+/// "`FirstError` cannot be broken into `SecondError`: missing variant `BarError`".
+///
+/// ```compile_fail,E0277
+/// # use throwing::{throws, define_error};
+/// # define_error!(type FooError);
+/// # define_error!(type BarError);
+/// #
+/// #[throws(FooError | BarError)]
+/// fn first() {
+///     unimplemented!("Some complicated implementation...")
+/// }
+///
+/// #[throws(FooError | break FirstError)]
+/// fn second() {
+///     first()?;
+///     Ok(())
+/// }
+/// ```
+///
+/// Suberrors don't have to be declared if they are converted with [`WidenResult::widen()`] first.
+/// All generated errors implement [`From<Widened<E>>`] for any `E` that implements [`SubError`]
+/// for them, so `first().widen()?` would work even without `break FirstError`.
+/// This also works inside functions with anonymous errors.
+/// In closures and other places where the return type doesn't implement [`From<Widened<E>>`],
+/// the [`widen!()`] macro can be used instead.
+/// To convert an error outside of the `?` operator, use [`WidenResult::widen_into()`],
+/// like `first().widen_into::<SecondError>()`.
+///
 /// The enum will also have a [`Display`] implementation that simply forwards
 /// to the [`Display`] implementation of each variant.
-/// It will also implement [`Error`] and provide the [`Error::source()`] method.
+/// It will also implement [`Error`] and provide the [`Error::source()`] method,
+/// and implement [`ThrowingError`] for inspecting its variants without knowing its type.
+/// A static description of all variants is available through [`ErrorMetadata`].
+/// For tests, it implements [`Inspect`][testing::Inspect], which [`assert_throws!()`] uses
+/// to check the type of the error held by the variant.
 ///
 /// # Syntax
 ///
@@ -166,14 +242,676 @@ pub use throwing_macros::define_error;
 /// The name of a variant can be generated automatically if the type is a simple path.
 /// The generated name will consist of the entire path converted to camel case.
 /// For example, `io:Error` will become `IoError` and `ParseIntError` will remain `ParseIntError`.
+/// Generic arguments are ignored, so `Box<dyn Error>` will become `Box`.
+///
+/// With the `last_two` and `last` naming strategies, only the last segments of the path are used
+/// and the name is derived from generic arguments as well:
+/// leading `crate`, `self` and `super` segments are skipped,
+/// generic arguments are prepended to the name, so `Error<Json>` will become `JsonError`,
+/// and references and types wrapped in [`Box`], [`Arc`][std::sync::Arc] or [`Rc`][std::rc::Rc]
+/// are named after the type they contain, so `Box<ParseError>` will become `ParseError`.
+/// If the generic arguments can't be named, they are ignored like with the default strategy.
 /// The name can be specified explicitly with the `as` keyword, for example `io::Error as InputError`.
 ///
+/// ```
+/// # use throwing::define_error;
+/// # use std::sync::Arc;
+/// define_error!(naming = last_two, type LoadError = std::io::Error | Arc<std::fmt::Error>);
+///
+/// fn describe(error: LoadError) -> &'static str {
+///     match error {
+///         LoadError::IoError(_) => "I/O error",
+///         LoadError::FmtError(_) => "formatting error",
+///     }
+/// }
+/// ```
+///
+/// Each variant must have a different name and type.
+/// Listing the same type twice or two types that are named the same is rejected
+/// with an error pointing at the second one, which can then be renamed with `as`.
+///
+/// ```compile_fail
+/// # use throwing::define_error;
+/// # mod a { pub type Error = std::io::Error; }
+/// # mod b { pub type Error = std::fmt::Error; }
+/// // error: duplicate variant name `Error`, use `as Name` to give one of the variants a different name
+/// define_error!(naming = last, type LoadError = a::Error | b::Error);
+/// ```
+///
+/// ```compile_fail
+/// # use throwing::define_error;
+/// # use std::io;
+/// // error: this type is listed more than once
+/// define_error!(type LoadError = io::Error | io::Error as OtherIoError);
+/// ```
+///
 /// The name of the error type can be generated automatically as well.
 /// It will be generated by converting the function name to camel case and appending "`Error`".
 /// For example, `fn upload_image()` will return a `UploadImageError`.
 /// The name can also be specified explicitly using `type SomeError = ...`.
 /// For example, to name your error type `SubmitError`, you should use
 /// `#[throws(type SubmitError = FooError | BarError)].`
+/// To use a different naming scheme, the `name` option accepts a template
+/// in which `{Fn}` is replaced by the function name converted to camel case.
+/// For example, `#[throws(name = "{Fn}Failure", FooError)]` on `fn upload_image()`
+/// will generate a `UploadImageFailure`.
+///
+/// If the `module` option is used, the error type will be placed in a module named after
+/// the function and it will be called `Error` unless named otherwise.
+/// This prevents collisions between functions with the same name in different modules.
+/// All items from the parent module are imported into the generated module
+/// and paths starting with `super` are adjusted accordingly.
+///
+/// ```
+/// # use throwing::throws;
+/// # use std::io;
+/// mod config {
+///     # use throwing::throws;
+///     # use std::{fs, io};
+///     #[throws(module, io::Error)]
+///     pub fn load() -> String {
+///         Ok(fs::read_to_string("config.toml")?)
+///     }
+/// }
+///
+/// #[throws(module, io::Error | break config::load::Error)]
+/// fn load() -> String {
+///     Ok(config::load()?)
+/// }
+///
+/// # fn main() {
+/// let result: Result<String, load::Error> = load();
+/// # }
+/// ```
+///
+/// A variant can also carry a typed context describing what was being done when the error occurred,
+/// for example `io::Error with ReadConfig`.
+/// It will contain a [`WithContext`] with both the error and the context,
+/// which can be created using [`Context::context()`], as in `read(path).context(ReadConfig { path })?`.
+/// It's displayed as just the context rather than combined with the error,
+/// and its source is the error, so printing the chain of sources shows both.
+/// Such variants are named after the context, so `io::Error with ReadConfig` becomes `ReadConfig`.
+/// Since the context is only used for its [`Display`] implementation,
+/// they can't have the `serde` or `response` annotations.
+/// See [`Context`] for an example.
+///
+/// ```compile_fail
+/// # use throwing::{throws, Context};
+/// # use std::{fs, io};
+/// // error: variants with context cannot have the `serde` or `response` annotations
+/// #[throws(io::Error with String [serde])]
+/// fn load_config(path: String) -> String {
+///     Ok(fs::read_to_string(&path).context(format!("failed to read {path}"))?)
+/// }
+/// ```
+///
+/// Simple errors that don't wrap another error can be declared inline with a message,
+/// either as a unit variant like `NotFound = "item not found"`
+/// or with fields like `TooLong { len: usize } = "length {len} exceeds limit"`.
+/// The message is a format string that can refer to the fields of the variant.
+/// A constructor named after the variant in snake case is generated for each inline variant,
+/// so the second variant can be created with `TooLong { len }` or `too_long(len)`.
+/// Since inline variants can't be converted into other errors,
+/// an error with inline variants doesn't implement [`SubError`].
+/// Using it as a suberror with `break` fails with an error saying so,
+/// and it can't be converted with [`WidenResult::widen()`] or [`widen!()`] either.
+/// Such an error can instead be wrapped in a variant of the other error,
+/// or its variants can be converted by hand.
+///
+/// ```
+/// # use throwing::define_error;
+/// # use std::num::ParseIntError;
+/// define_error!(pub type LookupError = ParseIntError
+///     | NotFound = "item not found"
+///     | TooLong { len: usize } = "length {len} exceeds limit");
+///
+/// fn lookup(key: &str) -> Result<u64, LookupError> {
+///     if key.len() > 8 {
+///         return Err(LookupError::too_long(key.len()));
+///     }
+///
+///     let _id: u64 = key.parse()?;
+///     Err(LookupError::NotFound)
+/// }
+///
+/// assert_eq!(lookup("123456789").unwrap_err().to_string(), "length 9 exceeds limit");
+/// ```
+///
+/// An error can contain itself, for example when evaluating an included file fails.
+/// Such a variant is written as `Self` or the name of the error type
+/// and it's stored in a [`Box`], since the enum would otherwise be infinitely sized,
+/// or in an [`Arc`][std::sync::Arc] if it's shared.
+/// The variant is named after the error type unless named otherwise
+/// and the error implements [`From<Box<Self>>`].
+/// Its source is the error inside the [`Box`].
+/// An error can't be its own suberror, so `break Self` is rejected.
+///
+/// ```
+/// # use throwing::throws;
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(io::Error | ParseIntError | Self as Include)]
+/// fn eval(source: &str) -> i64 {
+///     if let Some(path) = source.strip_prefix("include ") {
+///         let source = fs::read_to_string(path)?;
+///         return Ok(eval(&source).map_err(Box::new)?);
+///     }
+///
+///     Ok(source.trim().parse()?)
+/// }
+///
+/// # fn main() {
+/// if let Err(EvalError::Include(error)) = eval("include main.txt") {
+///     println!("failed to evaluate included file: {error}");
+/// }
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # use throwing::throws;
+/// # use std::num::ParseIntError;
+/// // error: an error cannot be a suberror of itself, remove `break` to add a boxed variant instead
+/// #[throws(ParseIntError | break Self)]
+/// fn eval(source: &str) -> i64 {
+///     Ok(source.trim().parse()?)
+/// }
+/// ```
+///
+/// Each variant can be followed by a list of annotations in square brackets,
+/// for example `io::Error as InputError [code = Input, serde]`.
+/// The following annotations are supported:
+///
+/// - `code = SomeCode`: Assigns an identifier to the variant, which is included when serializing it.
+/// - `grpc = NotFound`: Sets the gRPC status code used when converting the variant into a `Status`.
+///   It must be the name of a variant of `tonic::Code` and defaults to `Internal`.
+/// - `serde`: Marks the variant type as implementing `Serialize` and `Deserialize`.
+///   Not available for inline variants or variants with context.
+/// - `status = 404`: Sets the HTTP status code used when converting the variant into a response.
+/// - `response`: Requires the variant type to implement `IntoResponse`,
+///   which will be used instead of the default response.
+///   Without this annotation, the implementation is still used if it exists
+///   and the variant has no `status` annotation.
+///   Not available for inline variants or variants with context.
+/// - `exit = 2`: Sets the exit code of the process when `main` fails with the variant.
+///   It must not be 0, since that indicates success.
+/// - `io_kind = NotFound`: Sets the [`io::ErrorKind`] used when converting the variant
+///   into an [`io::Error`].
+/// - `shared`: Stores the variant in an [`Arc`][std::sync::Arc], see [below](#shared-variants).
+///   Not available together with `serde` or `response`.
+///
+/// The list of variants can be preceded by a comma-separated list of options,
+/// for example `#[throws(metadata = "error-kind", FooError | BarError)]`.
+/// The following options are supported:
+///
+/// - `metadata = "key"`: Attaches the name of the variant to gRPC statuses as the given metadata key.
+///   Variant names must be ASCII to be valid metadata values.
+/// - `report`: Changes the function to return an [`ExitCode`] and report errors using [`report()`].
+///   Only available for [`#[throws]`][throws] on functions without parameters.
+/// - `io_error`: Generates conversions to and from [`io::Error`].
+/// - `naming = last`: Changes how variant names are generated from paths.
+///   With `full`, the default, all segments are used, so `std::io::Error` becomes `StdIoError`.
+///   With `last_two`, only the last two segments are used, so it becomes `IoError`.
+///   With `last`, only the last segment is used, so it becomes just `Error`.
+/// - `name = "{Fn}Error"`: Sets the template used to generate the name of the error type.
+///   Only available for [`#[throws]`][throws].
+/// - `module`: Places the error type in a module named after the function.
+///   Only available for [`#[throws]`][throws].
+/// - `infer`: Adds the variants of called functions, see [below](#inferred-errors).
+///   Only available for [`#[throws]`][throws].
+/// - `inferable`: Allows the variants of the function's error to be inferred by callers,
+///   see [below](#inferred-errors).
+///   Only available for [`#[throws]`][throws].
+/// - `result = crate::Result`: Uses the given type instead of [`Result`] as the return type.
+///   It must accept the value type and the error type as generic arguments.
+///   Only available for [`#[throws]`][throws].
+/// - `nested`: Places the result inside the `Option<T>` or `Poll<T>` returned by the function,
+///   see [below](#nested-results).
+///   Only available for [`#[throws]`][throws].
+/// - `auto_ok`: Wraps the value of the function body in `Ok`, see [below](#automatic-ok).
+///   Only available for [`#[throws]`][throws].
+/// - `shared`: Marks all variants with the `shared` annotation.
+/// - `partial_eq`: Implements [`PartialEq`] by comparing the variants of errors and their messages,
+///   since the errors they contain usually don't implement it.
+///   Useful together with [`assert_throws!()`] in tests.
+///
+/// # Shared variants
+///
+/// Errors that need to be cloned, for example to be stored in a cache,
+/// can't contain errors like [`io::Error`] that don't implement [`Clone`].
+/// Variants with the `shared` annotation are therefore stored in an [`Arc`][std::sync::Arc]
+/// and an error with shared variants implements [`Clone`].
+/// The other variants of such an error must implement [`Clone`] as well,
+/// and a variant that doesn't is reported as an error at its type.
+/// The `shared` option stores all variants in an [`Arc`][std::sync::Arc] instead.
+/// Both the type and the [`Arc`][std::sync::Arc] containing it can be converted into the error
+/// and the source of the variant is the error inside the [`Arc`][std::sync::Arc].
+///
+/// ```
+/// # use throwing::throws;
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(shared, io::Error | ParseIntError)]
+/// fn load(path: &str) -> u64 {
+///     Ok(fs::read_to_string(path)?.trim().parse()?)
+/// }
+///
+/// # fn main() {
+/// let result = load("number.txt");
+/// let cached = result.clone();
+/// # }
+/// ```
+///
+/// # Mapping errors
+///
+/// Each generated error has a `fold` method, which takes a function for each variant
+/// and calls the one for the variant of the error.
+/// The functions are passed in the order in which the variants were listed,
+/// except that inline variants come last.
+/// Functions for inline variants receive the fields of the variant as arguments.
+///
+/// Errors without inline variants also have a `map_` method for each variant,
+/// like `map_io_error` for the `IoError` variant.
+/// It transforms the error in that variant using the given function
+/// and converts the result into another error,
+/// which must be possible to create from the result and from all other variants.
+///
+/// Since methods are named after the variants, variant names that only differ in case
+/// or underscores, like `HttpError` and `HTTPError`, are rejected.
+/// Inline variants also can't be named `Fold`, since their constructor would be named `fold`.
+///
+/// ```
+/// # use throwing::{define_error, throws};
+/// # use std::{fs, io, num::ParseIntError};
+/// define_error!(type ConfigError = ParseIntError | MissingConfig = "config not found");
+///
+/// #[throws(io::Error | ParseIntError)]
+/// fn read_limit() -> u64 {
+///     Ok(fs::read_to_string("limit.txt")?.trim().parse()?)
+/// }
+///
+/// fn limit() -> Result<u64, ConfigError> {
+///     read_limit().map_err(|e| e.map_io_error(|_| ConfigError::MissingConfig))
+/// }
+///
+/// fn describe(error: ConfigError) -> String {
+///     error.fold(|e| format!("invalid limit: {e}"), || "using default limit".to_owned())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use throwing::define_error;
+/// # use std::{fmt, io};
+/// // error: variant name `HTTPError` generates the same method names as `HttpError`,
+/// // use `as Name` to give one of the variants a different name
+/// define_error!(type FetchError = io::Error as HttpError | fmt::Error as HTTPError);
+/// ```
+///
+/// ```compile_fail
+/// # use throwing::define_error;
+/// # use std::num::ParseIntError;
+/// // error: the constructor of inline variant `Fold` would clash with the generated `fold` method
+/// define_error!(type ConfigError = ParseIntError | Fold = "cannot fold");
+/// ```
+///
+/// # Anonymous errors
+///
+/// For small helper functions, it may not be worth it to declare a named error type.
+/// Prefixing the list of variants with the `anon` keyword, as in `#[throws(anon FooError | BarError)]`,
+/// will make the function return a [`OneOf`] union instead of generating an enum.
+/// The variants of an anonymous error can't have names or annotations and suberrors aren't allowed.
+///
+/// Since [`From<T>`] can't be implemented for each type of a generic union,
+/// the macro rewrites all uses of the `?` operator in the function body to convert
+/// errors into the union.
+/// The operator can be applied to any of the listed errors or to another union with a subset
+/// of its types.
+/// Closures and async blocks inside the function are not rewritten.
+///
+/// A union can also be used as a suberror of a generated error.
+///
+/// ```
+/// # use throwing::{throws, OneOf};
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(anon io::Error | ParseIntError)]
+/// fn read_number() -> u64 {
+///     let content = fs::read_to_string("number.txt")?;
+///     Ok(content.trim().parse()?)
+/// }
+///
+/// #[throws(io::Error | ParseIntError | break OneOf<(io::Error, ParseIntError)>)]
+/// fn read_twice() -> (u64, u64) {
+///     let first = read_number()?;
+///     let second = read_number()?;
+///     Ok((first, second))
+/// }
+/// ```
+///
+/// # Inferred errors
+///
+/// With the `infer` option, variants can be taken from the errors of called functions.
+/// The macro looks for uses of the `?` operator on calls to other functions
+/// annotated with [`#[throws]`][throws] and adds their variants and the functions' errors
+/// as suberrors.
+/// Only functions called by a single identifier or by a path starting with
+/// `crate`, `self` or `super` are considered, and they must be defined in the same crate.
+/// To skip a call, for example to a function not annotated with [`#[throws]`][throws],
+/// store its result in a variable before applying the `?` operator.
+///
+/// The called functions must have the `inferable` option,
+/// which generates a hidden macro named `__throwing_` followed by the name of the function.
+/// The macro has the same visibility as the function, limited to the crate.
+/// Calling a function without the option fails with an error about the missing macro.
+///
+/// Errors of other expressions can't be inferred, so they must be listed explicitly.
+/// If no variants are listed, using the `?` operator on such an expression is an error.
+///
+/// Variants with the same name must have the same type, otherwise compilation fails,
+/// so errors of the called functions should name their variants consistently.
+/// Inferred variants can't have the same name as inline variants.
+///
+/// ```
+/// # use throwing::throws;
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(inferable, io::Error)]
+/// fn read() -> String {
+///     Ok(fs::read_to_string("number.txt")?)
+/// }
+///
+/// #[throws(inferable, ParseIntError)]
+/// fn parse(s: &str) -> u64 {
+///     Ok(s.trim().parse()?)
+/// }
+///
+/// #[throws(infer)]
+/// fn load() -> u64 {
+///     let content = read()?;
+///     Ok(parse(&content)?)
+/// }
+///
+/// # fn main() {
+/// match load() {
+///     Ok(_) => {}
+///     Err(LoadError::IoError(_)) => {}
+///     Err(LoadError::ParseIntError(_)) => {}
+/// }
+/// # }
+/// ```
+///
+/// # Automatic `Ok`
+///
+/// With the `auto_ok` option, the function body doesn't have to end with `Ok(...)`.
+/// Its tail expression and all values returned with `return` are wrapped in `Ok` instead,
+/// while errors are returned using the `?` operator or the [`throw!()`] macro.
+/// A body without a tail expression returns `Ok(())`.
+/// Like with anonymous errors, closures and async blocks inside the function are not rewritten.
+///
+/// ```
+/// # use throwing::{throw, throws};
+/// # use std::num::ParseIntError;
+/// #[throws(auto_ok, ParseIntError | Empty = "input is empty")]
+/// fn parse(s: &str) -> u64 {
+///     if s.is_empty() {
+///         throw!(ParseError::Empty);
+///     }
+///
+///     if s == "zero" {
+///         return 0;
+///     }
+///
+///     s.parse()?
+/// }
+///
+/// # fn main() {
+/// assert_eq!(parse("zero").unwrap(), 0);
+/// assert_eq!(parse("42").unwrap(), 42);
+/// # }
+/// ```
+///
+/// # Nested results
+///
+/// Methods like [`Iterator::next()`] and [`Future::poll()`][std::future::Future::poll]
+/// return a result wrapped in an [`Option`] or a [`Poll`][std::task::Poll].
+/// With the `nested` option, a function returning `Option<T>` will be changed to
+/// return `Option<Result<T, E>>` and one returning `Poll<T>` will return `Poll<Result<T, E>>`.
+///
+/// The `?` operator can be used on results in such functions.
+/// For `Poll`, this is supported by the standard library.
+/// For `Option`, the macro rewrites all uses of the operator in the function body,
+/// so that it returns `None` when applied to an option
+/// and `Some(Err(...))` with the converted error when applied to a result.
+/// Like with anonymous errors, closures and async blocks inside the function are not rewritten.
+///
+/// Since the macro can't place the error type inside an `impl` block,
+/// such methods should be placed in a block annotated with [`#[throws_module]`][throws_module].
+///
+/// ```
+/// # use throwing::{throws, throws_module};
+/// # use std::num::ParseIntError;
+/// struct Numbers<'a>(std::str::Split<'a, char>);
+///
+/// #[throws_module]
+/// impl Iterator for Numbers<'_> {
+///     type Item = Result<u64, NextError>;
+///
+///     #[throws(nested, ParseIntError)]
+///     fn next(&mut self) -> Option<u64> {
+///         let part = self.0.next()?;
+///         Some(Ok(part.trim().parse()?))
+///     }
+/// }
+///
+/// # fn main() {
+/// let numbers: Result<Vec<u64>, NextError> = Numbers("1, 2, 3".split(',')).collect();
+/// assert_eq!(numbers.unwrap(), [1, 2, 3]);
+/// # }
+/// ```
+///
+/// # Exit codes
+///
+/// Using [`#[throws]`][throws] on `main` without any options will use the default implementation
+/// of [`Termination`] for [`Result`], which prints the error using its [`Debug`] implementation
+/// and exits with code 1.
+///
+/// With the `report` option, the function is wrapped by another one that passes its result to
+/// [`report()`].
+/// It prints the error and its sources using their [`Display`] implementation
+/// and exits with the code set by the `exit` annotation of the variant.
+/// This makes it easy to follow conventions like [`sysexits.h`][sysexits].
+///
+/// ```
+/// # use throwing::{throws, ExitCodeError};
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(ParseIntError [exit = 65] | io::Error [exit = 74])]
+/// fn read_count() -> u64 {
+///     let content = fs::read_to_string("count.txt")?;
+///     Ok(content.trim().parse()?)
+/// }
+///
+/// #[throws(report, ParseIntError [exit = 65] | io::Error [exit = 74] | break ReadCountError)]
+/// fn main() {
+///     # let error = MainError::from("x".parse::<u8>().unwrap_err());
+///     # assert_eq!(error.exit_code(), 65);
+///     # if false {
+///     let count = read_count()?;
+///     println!("{count}");
+///     # }
+///     Ok(())
+/// }
+/// ```
+///
+/// [sysexits]: https://man.freebsd.org/cgi/man.cgi?query=sysexits
+///
+/// # I/O errors
+///
+/// Implementations of traits like [`Read`][io::Read] or [`Write`] need to return an [`io::Error`].
+/// The `io_error` option makes it easy to return a generated error from them
+/// by implementing [`From<T>`] for [`io::Error`].
+/// A variant containing an [`io::Error`] is converted by returning it directly,
+/// other variants are wrapped in an [`io::Error`]
+/// with the [`io::ErrorKind`] given by their `io_kind` annotation, or `Other` if it's missing.
+///
+/// It also generates a `try_from_io_error()` function, which recovers the original error
+/// from an [`io::Error`] it was converted into.
+/// An [`io::Error`] that doesn't wrap the generated error is converted
+/// into its [`io::Error`] variant, if it has one.
+///
+/// ```
+/// # use throwing::define_error;
+/// # use std::{io, num::ParseIntError};
+/// define_error!(io_error, type ReadRecordError = io::Error | ParseIntError [io_kind = InvalidData]);
+///
+/// let error = ReadRecordError::from("x".parse::<u8>().unwrap_err());
+/// let error = io::Error::from(error);
+/// assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+///
+/// let error = ReadRecordError::try_from_io_error(error).unwrap();
+/// assert!(matches!(error, ReadRecordError::ParseIntError(_)));
+///
+/// let error = io::Error::from(io::ErrorKind::NotFound);
+/// let error = ReadRecordError::try_from_io_error(error).unwrap();
+/// assert!(matches!(error, ReadRecordError::IoError(_)));
+/// ```
+///
+/// # Serde
+///
+/// With the `serde` feature enabled, all generated errors implement `Serialize`.
+/// Each error is serialized as a struct with a `kind` field containing the name of the variant
+/// and a `message` field containing its [`Display`] output.
+/// If the variant has a `code` annotation, it will be included in a `code` field.
+///
+/// Variants with the `serde` annotation additionally include the variant itself
+/// in an `error` field.
+/// If an error has at least one such variant, it will also implement `Deserialize`.
+/// Deserializing any variant without the annotation will fail.
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # fn main() {
+/// # use throwing::define_error;
+/// # use serde::{Deserialize, Serialize};
+/// # use std::{fmt, error::Error, num::ParseIntError};
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct QuotaError {
+///     limit: u32,
+/// }
+/// #
+/// # impl fmt::Display for QuotaError {
+/// #     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// #         write!(f, "quota of {} exceeded", self.limit)
+/// #     }
+/// # }
+/// #
+/// # impl Error for QuotaError {}
+///
+/// define_error!(type RequestError = ParseIntError [code = BadInput] | QuotaError [serde]);
+///
+/// let error = RequestError::from(QuotaError { limit: 5 });
+/// let json = serde_json::to_string(&error).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"kind":"QuotaError","message":"quota of 5 exceeded","error":{"limit":5}}"#
+/// );
+///
+/// let error: RequestError = serde_json::from_str(&json).unwrap();
+/// assert!(matches!(error, RequestError::QuotaError(QuotaError { limit: 5 })));
+///
+/// let error = RequestError::from("x".parse::<u8>().unwrap_err());
+/// let json = serde_json::to_string(&error).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"kind":"ParseIntError","message":"invalid digit found in string","code":"BadInput"}"#
+/// );
+/// # }
+/// # #[cfg(not(feature = "serde"))]
+/// # fn main() {}
+/// ```
+///
+/// # Axum
+///
+/// With the `axum` feature enabled, all generated errors implement `IntoResponse`.
+/// Variants whose type already implements `IntoResponse` are converted using that implementation,
+/// unless they have a `status` annotation.
+/// The `response` annotation can be used to make sure that the variant type implements it.
+/// All other variants are converted into a [problem details][rfc-9457] JSON response
+/// with the status code given by their `status` annotation, or 500 if it's missing.
+/// The body contains the [`Display`] output of the variant in the `detail` field,
+/// the name of the variant in the `kind` field and its `code` annotation, if any.
+///
+/// ```
+/// # #[cfg(feature = "axum")]
+/// # fn main() {
+/// # use throwing::define_error;
+/// # use std::{fmt, error::Error, io};
+/// use axum_core::response::IntoResponse;
+///
+/// #[derive(Debug)]
+/// struct NotFoundError;
+/// #
+/// # impl fmt::Display for NotFoundError {
+/// #     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// #         write!(f, "no such page")
+/// #     }
+/// # }
+/// #
+/// # impl Error for NotFoundError {}
+///
+/// define_error!(type PageError = NotFoundError [status = 404] | io::Error);
+/// define_error!(type AppError = PageError | fmt::Error);
+///
+/// let response = PageError::from(NotFoundError).into_response();
+/// assert_eq!(response.status(), 404);
+/// assert_eq!(response.headers()["content-type"], "application/problem+json");
+///
+/// let response = PageError::from(io::Error::other("disk on fire")).into_response();
+/// assert_eq!(response.status(), 500);
+///
+/// // `PageError` implements `IntoResponse`, so it's used for the variant
+/// let response = AppError::from(PageError::from(NotFoundError)).into_response();
+/// assert_eq!(response.status(), 404);
+/// # }
+/// # #[cfg(not(feature = "axum"))]
+/// # fn main() {}
+/// ```
+///
+/// [rfc-9457]: https://www.rfc-editor.org/rfc/rfc9457.html
+///
+/// # Tonic
+///
+/// With the `tonic` feature enabled, all generated errors can be converted into a `tonic::Status`.
+/// The status code is given by the `grpc` annotation of the variant, or `Internal` if it's missing.
+/// The message of the status is the [`Display`] output of the variant.
+/// If the `metadata` option is used, the name of the variant is attached
+/// to the status as metadata.
+///
+/// ```
+/// # #[cfg(feature = "tonic")]
+/// # fn main() {
+/// # use throwing::define_error;
+/// # use std::{fmt, error::Error, num::ParseIntError};
+/// use tonic::{Code, Status};
+///
+/// #[derive(Debug)]
+/// struct UnknownUserError;
+/// #
+/// # impl fmt::Display for UnknownUserError {
+/// #     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// #         write!(f, "no such user")
+/// #     }
+/// # }
+/// #
+/// # impl Error for UnknownUserError {}
+///
+/// define_error!(
+///     metadata = "error-kind",
+///     type GetUserError = UnknownUserError [grpc = NotFound] | ParseIntError [grpc = InvalidArgument]
+/// );
+///
+/// let status = Status::from(GetUserError::from(UnknownUserError));
+/// assert_eq!(status.code(), Code::NotFound);
+/// assert_eq!(status.message(), "no such user");
+/// assert_eq!(status.metadata().get("error-kind").unwrap(), "UnknownUserError");
+/// # }
+/// # #[cfg(not(feature = "tonic"))]
+/// # fn main() {}
+/// ```
 ///
 /// # Examples
 ///
@@ -223,15 +961,299 @@ pub use throwing_macros::define_error;
 /// ```
 pub use throwing_macros::throws;
 
+/// Declares a set of error types shared by all functions in a module or an `impl` block.
+///
+/// The macro takes a comma-separated list of aliases, each of which is assigned a type,
+/// for example `#[throws_module(Io = io::Error, Parse = ParseIntError)]`.
+/// Each type can be followed by a list of annotations in square brackets,
+/// which are used unless the function specifies its own.
+/// When used on a module, the types are resolved inside it.
+///
+/// Functions inside the module or `impl` block can then use the aliases
+/// in their [`#[throws]`][throws] attributes.
+/// A variant declared using an alias will be named after it, unless given a different name with `as`.
+/// The [`#[throws]`][throws] attributes are processed by this macro,
+/// so they don't need to be imported.
+///
+/// Additionally, if the variants of one function's error are a subset of the variants
+/// of another function's error, the first error is automatically added as a suberror of the second.
+/// This allows calling functions from the same module with the `?` operator
+/// without listing their errors with the `break` keyword.
+/// If two functions have exactly the same variants, only the error of the function declared
+/// first is added as a suberror of the other one.
+/// Each function can only have one [`#[throws]`][throws] attribute.
+///
+/// When used on an `impl` block, the error types are defined right after it.
+///
+/// # Examples
+///
+/// ```
+/// #[throwing::throws_module(Io = std::io::Error, Parse = std::num::ParseIntError)]
+/// mod config {
+///     # use std::fs;
+///     #[throws(Io)]
+///     pub fn read() -> String {
+///         Ok(fs::read_to_string("config.txt")?)
+///     }
+///
+///     #[throws(Parse)]
+///     pub fn parse(s: &str) -> u64 {
+///         Ok(s.trim().parse()?)
+///     }
+///
+///     #[throws(Io | Parse)]
+///     pub fn load() -> u64 {
+///         let content = read()?;
+///         Ok(parse(&content)?)
+///     }
+/// }
+///
+/// # fn main() {
+/// match config::load() {
+///     Ok(value) => println!("Loaded {value}"),
+///     Err(config::LoadError::Io(e)) => println!("Failed to read config: {e}"),
+///     Err(config::LoadError::Parse(e)) => println!("Failed to parse config: {e}"),
+/// }
+/// # }
+/// ```
+pub use throwing_macros::throws_module;
+
+/// Runs a block of code that can fail with one of the listed errors.
+///
+/// The list of variants is written the same way as for [`#[throws]`][throws]
+/// and is followed by `=>` and a block.
+/// The macro generates an error type local to the block, runs the block as a closure
+/// and evaluates to a [`Result`] with the generated error.
+/// The block must evaluate to a [`Result`] and the `?` operator can be used inside it.
+///
+/// Since the block runs as a closure, it behaves differently from a plain block:
+///
+/// - `return` only returns from the block, not from the enclosing function.
+/// - `break` and `continue` can't refer to loops outside the block.
+/// - `.await` can't be used inside the block, even in an `async` function.
+///
+/// By default, the error type is named `TryBlockError`.
+/// A different name can be set with `type SomeError = ...`, but in either case
+/// the type can't be named outside the block.
+/// It can still be converted into any error type that has all of its variants
+/// using [`WidenResult::widen()`] or [`SubError`].
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{throws, try_block, WidenResult};
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(io::Error | ParseIntError)]
+/// fn load() -> u64 {
+///     let path = "number.txt";
+///
+///     let number = try_block!(io::Error | ParseIntError => {
+///         let content = fs::read_to_string(path)?;
+///         Ok(content.trim().parse::<u64>()?)
+///     });
+///
+///     match &number {
+///         Ok(number) => println!("Read {number} from {path}"),
+///         Err(error) => println!("Failed to read {path}: {error}"),
+///     }
+///
+///     Ok(number.widen()?)
+/// }
+/// ```
+pub use throwing_macros::try_block;
+
+/// Returns an error from the current function.
+///
+/// `throw!(error)` expands to `return Err(From::from(error))`,
+/// so the error is converted into the error type of the function the same way
+/// as with the `?` operator.
+///
+/// Inside functions annotated with [`#[throws]`][throws], the macro is rewritten
+/// so that throwing a value that isn't one of the variants of the error,
+/// one of its suberrors or the error itself is reported with a message
+/// listing the types the error can be created from.
+/// In anonymous mode, the value is converted into the [`OneOf`] instead.
+/// Closures and async blocks inside the function still use [`From`].
+/// Only invocations written as `throw!()` or `throwing::throw!()` are rewritten,
+/// and since the rewritten macro still converts the error with [`From`],
+/// a different macro named `throw` that does the same keeps working.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{throw, throws};
+/// # use std::num::ParseIntError;
+/// #[throws(ParseIntError | Empty = "input is empty")]
+/// fn parse(s: &str) -> u64 {
+///     if s.is_empty() {
+///         throw!(ParseError::Empty);
+///     }
+///
+///     Ok(s.parse()?)
+/// }
+/// ```
+///
+/// Throwing an error that isn't one of the variants fails to compile:
+///
+/// ```compile_fail,E0277
+/// # use throwing::{throw, throws};
+/// # use std::{fmt, num::ParseIntError};
+/// #[throws(ParseIntError)]
+/// fn parse(s: &str) -> u64 {
+///     if s.is_empty() {
+///         // error: `std::fmt::Error` cannot be thrown as `ParseError`
+///         throw!(fmt::Error);
+///     }
+///
+///     Ok(s.parse()?)
+/// }
+/// ```
+#[macro_export]
+macro_rules! throw {
+    ($error:expr $(,)?) => {
+        return ::core::result::Result::Err(::core::convert::From::from($error))
+    };
+}
+
 /// Represents an error that can be upcasted to a different error type.
 /// 
 /// If type `A` implements `SubError<B>`, that means that `B` contains all
 /// variants that `A` has, and as such `A` can be converted to `B`.
 /// 
 /// This trait powers the [`#[throws]`][throws] and [`define_error!()`] macros.
-/// It's implemented by all error types they generate and required for suberrors
-/// declared with the `break` keyword.
+/// It's implemented by all error types they generate, except for those with inline variants,
+/// and required for suberrors declared with the `break` keyword.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be converted into `{T}`",
+    note = "all variants of `{Self}` must also be variants of `{T}` and errors with inline variants can't be converted"
+)]
 pub trait SubError<T> {
     /// Upcasts this error to a super type.
     fn to_super_error(self) -> T;
 }
+
+/// Represents an error that determines the exit code of a process.
+///
+/// It's implemented by all error types generated by the [`#[throws]`][throws]
+/// and [`define_error!()`] macros.
+/// The exit code of each variant can be set with the `exit` annotation,
+/// otherwise it's 1.
+pub trait ExitCodeError: Error {
+    /// Returns the exit code a process should exit with when failing with this error.
+    fn exit_code(&self) -> u8;
+}
+
+/// Represents an error generated by `throwing` that can be inspected without knowing its type.
+///
+/// It's implemented by all error types generated by the [`#[throws]`][throws]
+/// and [`define_error!()`] macros, which allows generic code like logging middleware
+/// to walk through them uniformly.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{throws, ThrowingError};
+/// # use std::{io, num::ParseIntError};
+/// #[throws(ParseIntError)]
+/// fn parse(s: &str) -> u64 {
+///     Ok(s.parse()?)
+/// }
+///
+/// #[throws(io::Error | ParseError)]
+/// fn load(s: &str) -> u64 {
+///     Ok(parse(s)?)
+/// }
+///
+/// fn variant_path(error: &dyn ThrowingError) -> String {
+///     match error.nested() {
+///         Some(nested) => format!("{}::{}", error.variant_name(), variant_path(nested)),
+///         None => error.variant_name().to_owned(),
+///     }
+/// }
+///
+/// let error = load("x").unwrap_err();
+/// assert_eq!(variant_path(&error), "ParseError::ParseIntError");
+/// assert_eq!(LoadError::variants(), ["IoError", "ParseError"]);
+/// ```
+pub trait ThrowingError: Error {
+    /// Returns the name of the variant of this error.
+    fn variant_name(&self) -> &'static str;
+
+    /// Returns the error contained in the variant, or [`None`] for inline variants.
+    ///
+    /// This is the same error as the one returned by [`Error::source()`].
+    /// It's an [`Option`] rather than `&dyn Error`, since inline variants don't contain an error.
+    fn inner(&self) -> Option<&(dyn Error + 'static)>;
+
+    /// Returns the name of the type of the error returned by [`ThrowingError::inner()`].
+    ///
+    /// For variants with context, shared and recursive variants, this is the type of the error
+    /// they hold, not the [`WithContext`], [`Arc`][std::sync::Arc] or [`Box`] containing it.
+    /// The name is produced by [`std::any::type_name()`] and has the same caveats.
+    fn variant_type_name(&self) -> Option<&'static str>;
+
+    /// Returns the error contained in the variant if it was also generated by `throwing`.
+    fn nested(&self) -> Option<&dyn ThrowingError>;
+
+    /// Returns the names of all variants of this error type.
+    fn variants() -> &'static [&'static str]
+    where
+        Self: Sized;
+}
+
+impl<T: ThrowingError> ThrowingError for Box<T> {
+    fn variant_name(&self) -> &'static str {
+        (**self).variant_name()
+    }
+
+    fn inner(&self) -> Option<&(dyn Error + 'static)> {
+        (**self).inner()
+    }
+
+    fn variant_type_name(&self) -> Option<&'static str> {
+        (**self).variant_type_name()
+    }
+
+    fn nested(&self) -> Option<&dyn ThrowingError> {
+        (**self).nested()
+    }
+
+    fn variants() -> &'static [&'static str] {
+        T::variants()
+    }
+}
+
+/// Converts the result of a `main` function into an [`ExitCode`].
+///
+/// If the result is an error, it will be printed to stderr using its [`Display`]
+/// implementation, followed by each error in its [`Error::source()`] chain.
+/// The process will then exit with the code returned by [`ExitCodeError::exit_code()`].
+///
+/// This function is used by functions generated with the `report` option
+/// of [`#[throws]`][throws].
+pub fn report<T: Termination, E: ExitCodeError>(result: Result<T, E>) -> ExitCode {
+    let error = match result {
+        Ok(value) => return value.report(),
+        Err(error) => error,
+    };
+
+    let mut stderr = io::stderr().lock();
+    let mut message = error.to_string();
+    let _ = writeln!(stderr, "Error: {message}");
+
+    let mut source = error.source();
+
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+
+        // Generated errors display the same message as their variants
+        if cause_message != message {
+            let _ = writeln!(stderr, "Caused by: {cause_message}");
+            message = cause_message;
+        }
+
+        source = cause.source();
+    }
+
+    ExitCode::from(error.exit_code())
+}
@@ -0,0 +1,53 @@
+/// Describes a variant of an error generated by `throwing`.
+///
+/// See [`ErrorMetadata`] for details.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VariantInfo {
+    /// The name of the variant.
+    pub name: &'static str,
+    /// The type the variant contains, as written in the macro,
+    /// or [`None`] for inline variants.
+    ///
+    /// It doesn't include the context of the variant or the [`Arc`][std::sync::Arc]
+    /// or [`Box`] the variant is stored in, and recursive variants are named after the error.
+    pub type_name: Option<&'static str>,
+    /// The message of an inline variant.
+    pub message: Option<&'static str>,
+    /// The file the variant was declared in.
+    pub file: &'static str,
+    /// The line the variant was declared on.
+    pub line: u32,
+    /// The value of the `code` annotation of the variant.
+    pub code: Option<&'static str>,
+    /// The value of the `status` annotation of the variant.
+    pub status: Option<u16>,
+}
+
+/// Provides a static description of the variants of an error.
+///
+/// It's implemented by all error types generated by the [`#[throws]`][crate::throws]
+/// and [`define_error!()`][crate::define_error] macros.
+/// This can be used to generate documentation or API schemas
+/// listing which errors each function can return.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{define_error, ErrorMetadata};
+/// # use std::io;
+/// define_error!(type LoadError = io::Error [code = Unavailable, status = 503]
+///     | NotFound = "config not found" [status = 404]);
+///
+/// for variant in LoadError::VARIANTS {
+///     println!("{} ({}:{})", variant.name, variant.file, variant.line);
+/// }
+///
+/// assert_eq!(LoadError::VARIANTS[0].type_name, Some("io::Error"));
+/// assert_eq!(LoadError::VARIANTS[0].code, Some("Unavailable"));
+/// assert_eq!(LoadError::VARIANTS[1].message, Some("config not found"));
+/// assert_eq!(LoadError::VARIANTS[1].status, Some(404));
+/// ```
+pub trait ErrorMetadata {
+    /// The variants of the error, with inline variants last.
+    const VARIANTS: &'static [VariantInfo];
+}
@@ -0,0 +1,403 @@
+//! Anonymous error types that can hold one of several errors.
+//!
+//! See [`OneOf`] for details.
+
+use std::{
+    convert::Infallible,
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use crate::{
+    __private::{cast, Upcast, Variants},
+    SubError,
+};
+
+/// An anonymous error type that holds one of the errors listed in a tuple.
+///
+/// For example, `OneOf<(io::Error, ParseIntError)>` holds either
+/// an [`io::Error`][std::io::Error] or a [`ParseIntError`][std::num::ParseIntError].
+/// Unions of up to eight errors are supported.
+///
+/// Unlike errors generated by [`define_error!()`][crate::define_error],
+/// it doesn't need to be declared beforehand,
+/// but its variants can't be matched on directly.
+/// Instead, they can be extracted with [`OneOf::downcast()`],
+/// or one at a time with [`Take::take()`], which narrows the type of the union.
+///
+/// A union can be converted with [`SubError`] into any generated error
+/// that has all of its variants, and with [`OneOf::widen()`] into any other union that does.
+///
+/// The `#[throws(anon ...)]` form of [`#[throws]`][crate::throws] uses this type
+/// instead of generating an enum.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{one_of::Take, OneOf};
+/// # use std::{io, num::ParseIntError};
+/// fn parse(s: &str) -> Result<u64, OneOf<(io::Error, ParseIntError)>> {
+///     s.parse().map_err(OneOf::new)
+/// }
+///
+/// let error = parse("x").unwrap_err();
+/// assert_eq!(error.to_string(), "invalid digit found in string");
+///
+/// let error = match error.downcast::<io::Error>() {
+///     Ok(_) => unreachable!(),
+///     Err(error) => error,
+/// };
+///
+/// let error = error.take::<ParseIntError>().unwrap();
+/// ```
+pub struct OneOf<T: Union> {
+    repr: T::Repr,
+}
+
+impl<T: Union> OneOf<T> {
+    /// Creates a union holding the given error.
+    ///
+    /// The type of the error must be one of the types listed in `T`.
+    pub fn new<U, I>(error: U) -> Self
+    where
+        T: Contains<U, I>,
+    {
+        OneOf {
+            repr: T::inject(error),
+        }
+    }
+
+    /// Extracts the error, if it's of type `U`.
+    ///
+    /// Unlike [`Take::take()`], the type of the union is kept if it fails,
+    /// so `U` doesn't have to be one of the types listed in `T`.
+    pub fn downcast<U: Error + 'static>(self) -> Result<U, Self> {
+        T::downcast(self.repr).map_err(|repr| OneOf { repr })
+    }
+
+    /// Returns a reference to the error, if it's of type `U`.
+    pub fn downcast_ref<U: Error + 'static>(&self) -> Option<&U> {
+        self.as_error().downcast_ref()
+    }
+
+    /// Converts this union into a union of a superset of its types.
+    pub fn widen<U: Union, I>(self) -> OneOf<U>
+    where
+        T: SubsetOf<U, I>,
+    {
+        OneOf {
+            repr: T::widen(self.repr),
+        }
+    }
+
+    /// Returns a reference to the held error.
+    pub fn as_error(&self) -> &(dyn Error + 'static) {
+        T::as_error(&self.repr)
+    }
+}
+
+/// Narrows a [`OneOf`] by extracting one of its types.
+///
+/// The `I` parameter is used to tell apart different positions in the tuple
+/// and is always inferred, so only the type to extract has to be specified,
+/// as in `error.take::<ParseIntError>()`.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{one_of::Take, OneOf};
+/// # use std::{io, num::ParseIntError};
+/// let error: OneOf<(io::Error, ParseIntError)> = OneOf::new("x".parse::<u8>().unwrap_err());
+///
+/// let error = match error.take::<io::Error>() {
+///     Ok(_) => unreachable!(),
+///     Err(error) => error,
+/// };
+///
+/// let error = error.take::<ParseIntError>().unwrap();
+/// assert_eq!(error.to_string(), "invalid digit found in string");
+/// ```
+pub trait Take<I> {
+    /// The tuple of types the union can hold.
+    type Union: Union;
+
+    /// Extracts the error, if it's of type `U`.
+    ///
+    /// Otherwise returns a union of the remaining types.
+    fn take<U>(self) -> Result<U, OneOf<<Self::Union as Contains<U, I>>::Rest>>
+    where
+        Self::Union: Contains<U, I>;
+}
+
+impl<T: Union, I> Take<I> for OneOf<T> {
+    type Union = T;
+
+    fn take<U>(self) -> Result<U, OneOf<T::Rest>>
+    where
+        T: Contains<U, I>,
+    {
+        T::take(self.repr).map_err(|repr| OneOf { repr })
+    }
+}
+
+impl<T: Union> Display for OneOf<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.as_error(), f)
+    }
+}
+
+impl<T: Union> Debug for OneOf<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OneOf").field(&self.as_error()).finish()
+    }
+}
+
+impl<T: Union> Error for OneOf<T> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.as_error())
+    }
+}
+
+impl<T: SubsetOf<U, I>, U: Union, I> Upcast<OneOf<U>, Subset<I>> for OneOf<T> {
+    fn upcast(self) -> OneOf<U> {
+        self.widen()
+    }
+}
+
+/// A tuple of error types that can be used with [`OneOf`].
+///
+/// This trait is implemented for tuples of up to eight types implementing [`Error`].
+pub trait Union: Sized {
+    #[doc(hidden)]
+    type Repr;
+
+    #[doc(hidden)]
+    fn as_error(repr: &Self::Repr) -> &(dyn Error + 'static);
+
+    #[doc(hidden)]
+    fn downcast<U: 'static>(repr: Self::Repr) -> Result<U, Self::Repr>;
+}
+
+/// A union that contains the type `T`.
+///
+/// The `I` parameter is used to tell apart different positions in the tuple
+/// and is always inferred.
+pub trait Contains<T, I>: Union {
+    /// The union of the remaining types.
+    type Rest: Union;
+
+    #[doc(hidden)]
+    fn inject(value: T) -> Self::Repr;
+
+    #[doc(hidden)]
+    fn take(repr: Self::Repr) -> Result<T, <Self::Rest as Union>::Repr>;
+}
+
+/// A union whose types are all contained in `U`.
+///
+/// The `I` parameter is used to tell apart different positions in the tuple
+/// and is always inferred.
+pub trait SubsetOf<U: Union, I>: Union {
+    #[doc(hidden)]
+    fn widen(repr: Self::Repr) -> U::Repr;
+}
+
+/// Marks an implementation of [`SubError`] that converts a union into another union.
+#[doc(hidden)]
+pub struct Subset<I>(I);
+
+macro_rules! indices {
+    ($($index:ident),*) => {
+        $(
+            #[doc(hidden)]
+            pub struct $index;
+        )*
+    };
+}
+
+indices!(I0, I1, I2, I3, I4, I5, I6, I7);
+
+impl Union for () {
+    type Repr = Infallible;
+
+    fn as_error(repr: &Infallible) -> &(dyn Error + 'static) {
+        match *repr {}
+    }
+
+    fn downcast<U: 'static>(repr: Infallible) -> Result<U, Infallible> {
+        match repr {}
+    }
+}
+
+impl<U: Union> SubsetOf<U, ()> for () {
+    fn widen(repr: Infallible) -> U::Repr {
+        match repr {}
+    }
+}
+
+impl Variants for OneOf<()> {
+    type List = ();
+}
+
+impl<Super> SubError<Super> for OneOf<()> {
+    fn to_super_error(self) -> Super {
+        match self.repr {}
+    }
+}
+
+impl<A: Error + 'static> Contains<A, I0> for (A,) {
+    type Rest = ();
+
+    fn inject(value: A) -> (Option<A>,) {
+        (Some(value),)
+    }
+
+    fn take(repr: (Option<A>,)) -> Result<A, Infallible> {
+        Ok(repr.0.expect("union should hold a value"))
+    }
+}
+
+macro_rules! list {
+    () => { () };
+    ($first:ident $(, $rest:ident)*) => { ($first, list!($($rest),*)) };
+}
+
+macro_rules! union {
+    ($($typ:ident $var:ident $index:ident),+) => {
+        impl<$($typ: Error + 'static),+> Union for ($($typ,)+) {
+            type Repr = ($(Option<$typ>,)+);
+
+            fn as_error(repr: &Self::Repr) -> &(dyn Error + 'static) {
+                let ($($var,)+) = repr;
+                $(
+                    if let Some($var) = $var {
+                        return $var;
+                    }
+                )+
+                unreachable!("union should hold a value")
+            }
+
+            fn downcast<U: 'static>(repr: Self::Repr) -> Result<U, Self::Repr> {
+                let ($(mut $var,)+) = repr;
+                $(
+                    if let Some(value) = $var.take() {
+                        match cast(value) {
+                            Ok(value) => return Ok(value),
+                            Err(value) => $var = Some(value),
+                        }
+                    }
+                )+
+                Err(($($var,)+))
+            }
+        }
+
+        impl<$($typ: Error + 'static,)+ U: Union, $($index),+> SubsetOf<U, ($($index,)+)> for ($($typ,)+)
+        where
+            $(U: Contains<$typ, $index>),+
+        {
+            fn widen(repr: Self::Repr) -> U::Repr {
+                let ($($var,)+) = repr;
+                $(
+                    if let Some($var) = $var {
+                        return <U as Contains<$typ, $index>>::inject($var);
+                    }
+                )+
+                unreachable!("union should hold a value")
+            }
+        }
+
+        impl<$($typ: Error + 'static),+> Variants for OneOf<($($typ,)+)> {
+            type List = list!($($typ),+);
+        }
+
+        impl<$($typ: Error + 'static,)+ Super> SubError<Super> for OneOf<($($typ,)+)>
+        where
+            $(Super: From<$typ>),+
+        {
+            fn to_super_error(self) -> Super {
+                let ($($var,)+) = self.repr;
+                $(
+                    if let Some($var) = $var {
+                        return Super::from($var);
+                    }
+                )+
+                unreachable!("union should hold a value")
+            }
+        }
+    };
+}
+
+macro_rules! contains {
+    (($($typ:ident $var:ident),+) $index:ident: $member:ident $slot:ident => ($($rest:ident $rest_var:ident),+)) => {
+        impl<$($typ: Error + 'static),+> Contains<$member, $index> for ($($typ,)+) {
+            type Rest = ($($rest,)+);
+
+            #[allow(unused_mut, unused_assignments)]
+            fn inject(value: $member) -> Self::Repr {
+                let ($(mut $var,)+) = <Self::Repr as Default>::default();
+                $slot = Some(value);
+                ($($var,)+)
+            }
+
+            fn take(repr: Self::Repr) -> Result<$member, <Self::Rest as Union>::Repr> {
+                let ($($var,)+) = repr;
+
+                match $slot {
+                    Some(value) => Ok(value),
+                    None => Err(($($rest_var,)+)),
+                }
+            }
+        }
+    };
+}
+
+union!(A a IA);
+union!(A a IA, B b IB);
+union!(A a IA, B b IB, C c IC);
+union!(A a IA, B b IB, C c IC, D d ID);
+union!(A a IA, B b IB, C c IC, D d ID, E e IE);
+union!(A a IA, B b IB, C c IC, D d ID, E e IE, F f IF);
+union!(A a IA, B b IB, C c IC, D d ID, E e IE, F f IF, G g IG);
+union!(A a IA, B b IB, C c IC, D d ID, E e IE, F f IF, G g IG, H h IH);
+
+contains!((A a, B b) I0: A a => (B b));
+contains!((A a, B b) I1: B b => (A a));
+
+contains!((A a, B b, C c) I0: A a => (B b, C c));
+contains!((A a, B b, C c) I1: B b => (A a, C c));
+contains!((A a, B b, C c) I2: C c => (A a, B b));
+
+contains!((A a, B b, C c, D d) I0: A a => (B b, C c, D d));
+contains!((A a, B b, C c, D d) I1: B b => (A a, C c, D d));
+contains!((A a, B b, C c, D d) I2: C c => (A a, B b, D d));
+contains!((A a, B b, C c, D d) I3: D d => (A a, B b, C c));
+
+contains!((A a, B b, C c, D d, E e) I0: A a => (B b, C c, D d, E e));
+contains!((A a, B b, C c, D d, E e) I1: B b => (A a, C c, D d, E e));
+contains!((A a, B b, C c, D d, E e) I2: C c => (A a, B b, D d, E e));
+contains!((A a, B b, C c, D d, E e) I3: D d => (A a, B b, C c, E e));
+contains!((A a, B b, C c, D d, E e) I4: E e => (A a, B b, C c, D d));
+
+contains!((A a, B b, C c, D d, E e, F f) I0: A a => (B b, C c, D d, E e, F f));
+contains!((A a, B b, C c, D d, E e, F f) I1: B b => (A a, C c, D d, E e, F f));
+contains!((A a, B b, C c, D d, E e, F f) I2: C c => (A a, B b, D d, E e, F f));
+contains!((A a, B b, C c, D d, E e, F f) I3: D d => (A a, B b, C c, E e, F f));
+contains!((A a, B b, C c, D d, E e, F f) I4: E e => (A a, B b, C c, D d, F f));
+contains!((A a, B b, C c, D d, E e, F f) I5: F f => (A a, B b, C c, D d, E e));
+
+contains!((A a, B b, C c, D d, E e, F f, G g) I0: A a => (B b, C c, D d, E e, F f, G g));
+contains!((A a, B b, C c, D d, E e, F f, G g) I1: B b => (A a, C c, D d, E e, F f, G g));
+contains!((A a, B b, C c, D d, E e, F f, G g) I2: C c => (A a, B b, D d, E e, F f, G g));
+contains!((A a, B b, C c, D d, E e, F f, G g) I3: D d => (A a, B b, C c, E e, F f, G g));
+contains!((A a, B b, C c, D d, E e, F f, G g) I4: E e => (A a, B b, C c, D d, F f, G g));
+contains!((A a, B b, C c, D d, E e, F f, G g) I5: F f => (A a, B b, C c, D d, E e, G g));
+contains!((A a, B b, C c, D d, E e, F f, G g) I6: G g => (A a, B b, C c, D d, E e, F f));
+
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I0: A a => (B b, C c, D d, E e, F f, G g, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I1: B b => (A a, C c, D d, E e, F f, G g, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I2: C c => (A a, B b, D d, E e, F f, G g, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I3: D d => (A a, B b, C c, E e, F f, G g, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I4: E e => (A a, B b, C c, D d, F f, G g, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I5: F f => (A a, B b, C c, D d, E e, G g, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I6: G g => (A a, B b, C c, D d, E e, F f, H h));
+contains!((A a, B b, C c, D d, E e, F f, G g, H h) I7: H h => (A a, B b, C c, D d, E e, F f, G g));
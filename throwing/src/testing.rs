@@ -0,0 +1,68 @@
+//! Helpers for asserting which errors a function returns in tests.
+//!
+//! See [`assert_throws!()`][crate::assert_throws] for details.
+
+use std::error::Error;
+
+use crate::{one_of::Union, OneOf};
+
+/// Errors whose active variant can be checked by type.
+///
+/// This trait is implemented for all errors generated by [`#[throws]`][crate::throws]
+/// and [`define_error!()`][crate::define_error], as well as for [`OneOf`] unions.
+pub trait Inspect {
+    /// Returns whether the error holds an error of type `T`.
+    ///
+    /// Variants with context are checked by their error instead of the whole
+    /// [`WithContext`][crate::WithContext], shared and recursive variants by the type
+    /// inside the [`Arc`][std::sync::Arc] or [`Box`], and inline variants never hold another error.
+    fn holds<T: Error + 'static>(&self) -> bool;
+}
+
+impl<U: Union> Inspect for OneOf<U> {
+    fn holds<T: Error + 'static>(&self) -> bool {
+        self.as_error().is::<T>()
+    }
+}
+
+/// Asserts that a [`Result`] is an error holding an error of the given type.
+///
+/// The error is returned, so that it can be inspected further.
+/// On failure, the macro panics with the [`Debug`][std::fmt::Debug] representation of the error.
+/// The error must implement [`Inspect`][crate::testing::Inspect].
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{assert_throws, throws};
+/// # use std::{io, num::ParseIntError};
+/// #[throws(io::Error | ParseIntError)]
+/// fn parse(s: &str) -> u64 {
+///     Ok(s.parse()?)
+/// }
+///
+/// let error = assert_throws!(parse("x"), ParseIntError);
+/// assert_eq!(error.to_string(), "invalid digit found in string");
+/// ```
+#[macro_export]
+macro_rules! assert_throws {
+    ($result:expr, $typ:ty $(,)?) => {
+        match $result {
+            ::core::result::Result::Ok(_) => ::core::panic!(
+                "expected an error of type `{}`, got `Ok`",
+                ::core::stringify!($typ),
+            ),
+            ::core::result::Result::Err(error) => {
+                if !$crate::testing::Inspect::holds::<$typ>(&error) {
+                    ::core::panic!(
+                        "expected an error of type `{}`, got {:?}",
+                        ::core::stringify!($typ),
+                        error,
+                    );
+                }
+
+                error
+            }
+        }
+    };
+}
@@ -0,0 +1,164 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use crate::SubError;
+
+/// An error that's being converted into a super error.
+///
+/// Errors generated by [`#[throws]`][crate::throws] and [`define_error!()`][crate::define_error]
+/// implement [`From<Widened<E>>`] for every `E` that implements [`SubError`][crate::SubError]
+/// for them.
+/// This allows the `?` operator to convert any suberror into them,
+/// even if it isn't declared with the `break` keyword.
+///
+/// Values of this type are created by [`Widen::widen()`] and [`WidenResult::widen()`].
+/// The wrapper is needed because the `?` operator converts errors with [`From`],
+/// which can't be implemented for every suberror directly without overlapping
+/// with the other conversions of the generated error.
+/// When the target type is known, [`Widen::widen_into()`] and [`WidenResult::widen_into()`]
+/// convert into it directly instead.
+pub struct Widened<E>(pub E);
+
+impl<E> Widened<E> {
+    /// Returns the wrapped error.
+    pub fn into_inner(self) -> E {
+        self.0
+    }
+}
+
+impl<E: Display> Display for Widened<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<E: Debug> Debug for Widened<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Widened").field(&self.0).finish()
+    }
+}
+
+impl<E: Error> Error for Widened<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// Extension trait for converting an error into any of its super errors.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{define_error, Widen};
+/// # use std::{io, num::ParseIntError};
+/// define_error!(type ParseError = ParseIntError);
+/// define_error!(type LoadError = io::Error | ParseIntError);
+///
+/// let error = ParseError::from("x".parse::<u8>().unwrap_err());
+/// let error: LoadError = error.widen().into();
+///
+/// let error = ParseError::from("x".parse::<u8>().unwrap_err());
+/// let error = error.widen_into::<LoadError>();
+/// ```
+pub trait Widen: Sized {
+    /// Marks this error for conversion into a super error.
+    fn widen(self) -> Widened<Self>;
+
+    /// Converts this error into the super error `T`.
+    fn widen_into<T>(self) -> T
+    where
+        Self: SubError<T>,
+    {
+        self.to_super_error()
+    }
+}
+
+impl<E: Error> Widen for E {
+    fn widen(self) -> Widened<Self> {
+        Widened(self)
+    }
+}
+
+/// Extension trait for converting the error of a [`Result`] into any of its super errors.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{throws, WidenResult};
+/// # use std::{fs, io, num::ParseIntError};
+/// #[throws(ParseIntError)]
+/// fn parse(s: &str) -> u64 {
+///     Ok(s.trim().parse()?)
+/// }
+///
+/// // The ParseError suberror doesn't have to be declared with `break`
+/// #[throws(io::Error | ParseIntError)]
+/// fn load() -> u64 {
+///     let content = fs::read_to_string("number.txt")?;
+///     Ok(parse(&content).widen()?)
+/// }
+///
+/// let result: Result<u64, LoadError> = parse("x").widen_into();
+/// assert!(matches!(result, Err(LoadError::ParseIntError(_))));
+/// ```
+pub trait WidenResult<T, E> {
+    /// Marks the error of this result for conversion into a super error.
+    fn widen(self) -> Result<T, Widened<E>>;
+
+    /// Converts the error of this result into the super error `S`.
+    fn widen_into<S>(self) -> Result<T, S>
+    where
+        E: SubError<S>;
+}
+
+impl<T, E> WidenResult<T, E> for Result<T, E> {
+    fn widen(self) -> Result<T, Widened<E>> {
+        self.map_err(Widened)
+    }
+
+    fn widen_into<S>(self) -> Result<T, S>
+    where
+        E: SubError<S>,
+    {
+        self.map_err(SubError::to_super_error)
+    }
+}
+
+/// Unwraps a [`Result`] or returns its error converted into a super error.
+///
+/// This works like the `?` operator, except that it uses [`SubError`][crate::SubError]
+/// instead of [`From`] to convert the error.
+/// It can be used in places where the type being returned doesn't implement
+/// [`From<Widened<E>>`], such as closures returning a [`OneOf`][crate::OneOf]
+/// or types from other crates.
+///
+/// # Examples
+///
+/// ```
+/// # use throwing::{define_error, widen, OneOf};
+/// # use std::num::ParseIntError;
+/// define_error!(type ParseError = ParseIntError);
+///
+/// fn parse(s: &str) -> Result<u8, ParseError> {
+///     Ok(s.parse()?)
+/// }
+///
+/// let sum = || -> Result<u8, OneOf<(ParseIntError, std::fmt::Error)>> {
+///     Ok(widen!(parse("1")) + widen!(parse("2")))
+/// };
+///
+/// assert_eq!(sum().unwrap(), 3);
+/// ```
+#[macro_export]
+macro_rules! widen {
+    ($result:expr $(,)?) => {
+        match $result {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(error) => {
+                return ::core::result::Result::Err($crate::__private::Upcast::upcast(error));
+            }
+        }
+    };
+}